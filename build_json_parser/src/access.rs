@@ -0,0 +1,115 @@
+//! Typed field lookups on parsed objects, for loading configs without a
+//! serde layer.
+
+use alloc::format;
+use alloc::string::String;
+
+use crate::error::ParseError;
+use crate::value::JsonValue;
+
+// Looks up `key`, reporting a missing field (or a non-object `value`)
+fn field<'a>(value: &'a JsonValue, key: &str) -> Result<&'a JsonValue, ParseError> {
+    match value {
+        JsonValue::Object(_) => value.get(key).ok_or_else(|| ParseError::Validation(format!("field `{}` missing", key))),
+        _ => Err(ParseError::Validation(format!("expected object with field `{}`", key))),
+    }
+}
+
+fn expected(key: &str, kind: &str) -> ParseError {
+    ParseError::Validation(format!("field `{}` expected {}", key, kind))
+}
+
+/// Returns the string field `key` of an object.
+pub fn get_string(value: &JsonValue, key: &str) -> Result<String, ParseError> {
+    match field(value, key)? {
+        JsonValue::String(s) => Ok(s.clone()),
+        _ => Err(expected(key, "string")),
+    }
+}
+
+/// Returns the numeric field `key`, which must be a whole number that fits
+/// in an `i64`.
+pub fn get_i64(value: &JsonValue, key: &str) -> Result<i64, ParseError> {
+    match field(value, key)? {
+        // i64::MAX as f64 rounds up to 2^63, which is out of range
+        JsonValue::Number(n) if *n >= i64::MIN as f64 && *n < i64::MAX as f64 && (*n as i64) as f64 == *n => Ok(*n as i64),
+        JsonValue::Number(_) => Err(expected(key, "integer")),
+        _ => Err(expected(key, "number")),
+    }
+}
+
+/// Returns the boolean field `key` of an object.
+pub fn get_bool(value: &JsonValue, key: &str) -> Result<bool, ParseError> {
+    match field(value, key)? {
+        JsonValue::Boolean(b) => Ok(*b),
+        _ => Err(expected(key, "boolean")),
+    }
+}
+
+/// Returns the elements of the array field `key`.
+pub fn get_array<'a>(value: &'a JsonValue, key: &str) -> Result<&'a [JsonValue], ParseError> {
+    match field(value, key)? {
+        JsonValue::Array(elements) => Ok(elements),
+        _ => Err(expected(key, "array")),
+    }
+}
+
+/// Whether a number lies between `min` and `max`, both inclusive. Gives
+/// `None` when `value` isn't a number, so callers can tell a wrong type
+/// from an out-of-range value.
+pub fn number_in_range(value: &JsonValue, min: f64, max: f64) -> Option<bool> {
+    match value {
+        JsonValue::Number(n) => Some(*n >= min && *n <= max),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn config() -> JsonValue {
+        parse(r#"{"host": "localhost", "port": 8080, "debug": false, "ratio": 0.5, "peers": ["a", "b"]}"#).unwrap()
+    }
+
+    #[test]
+    fn test_present_fields() {
+        let config = config();
+        assert_eq!(get_string(&config, "host").unwrap(), "localhost");
+        assert_eq!(get_i64(&config, "port").unwrap(), 8080);
+        assert!(!get_bool(&config, "debug").unwrap());
+        assert_eq!(get_array(&config, "peers").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_missing_fields() {
+        let config = config();
+        assert_eq!(get_i64(&config, "timeout").unwrap_err(), ParseError::Validation("field `timeout` missing".to_string()));
+        assert_eq!(get_string(&parse("[]").unwrap(), "host").unwrap_err().message(), "expected object with field `host`");
+    }
+
+    #[test]
+    fn test_wrong_typed_fields() {
+        let config = config();
+        assert_eq!(get_i64(&config, "host").unwrap_err().message(), "field `host` expected number");
+        assert_eq!(get_i64(&config, "ratio").unwrap_err().message(), "field `ratio` expected integer");
+        assert_eq!(get_string(&config, "port").unwrap_err().message(), "field `port` expected string");
+        assert_eq!(get_bool(&config, "peers").unwrap_err().message(), "field `peers` expected boolean");
+        assert_eq!(get_array(&config, "debug").unwrap_err().message(), "field `debug` expected array");
+    }
+
+    #[test]
+    fn test_number_in_range() {
+        let config = config();
+        let port = config.get("port").unwrap();
+        assert_eq!(number_in_range(port, 1.0, 65535.0), Some(true));
+        assert_eq!(number_in_range(port, 8080.0, 8080.0), Some(true));
+        assert_eq!(number_in_range(config.get("ratio").unwrap(), 0.0, 0.25), Some(false));
+        assert_eq!(number_in_range(&JsonValue::Number(-1.0), 0.0, 10.0), Some(false));
+
+        assert_eq!(number_in_range(config.get("host").unwrap(), 0.0, 10.0), None);
+        assert_eq!(number_in_range(&parse(r#""5""#).unwrap(), 0.0, 10.0), None);
+        assert_eq!(number_in_range(&JsonValue::Null, 0.0, 10.0), None);
+    }
+}
@@ -0,0 +1,69 @@
+//! Test-only global allocator that counts what the current thread
+//! allocates, for tests that make claims about memory use.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+// Counts per thread, so tests running in parallel don't disturb each
+// other's numbers
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static DEALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    // Signed because a thread can free memory another thread allocated
+    static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+    static PEAK_BYTES: Cell<isize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        let _ = LIVE_BYTES.try_with(|bytes| {
+            bytes.set(bytes.get() + layout.size() as isize);
+            let _ = PEAK_BYTES.try_with(|peak| peak.set(peak.get().max(bytes.get())));
+        });
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let _ = DEALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        let _ = LIVE_BYTES.try_with(|bytes| bytes.set(bytes.get() - layout.size() as isize));
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Number of allocations `f` makes, reallocations included.
+pub(crate) fn allocations_during<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+/// Runs `f` and returns its result along with how many of the allocations
+/// made while it ran are still alive, i.e. held by the result.
+pub(crate) fn retained_allocations<T, F: FnOnce() -> T>(f: F) -> (T, usize) {
+    let allocated = ALLOCATIONS.with(Cell::get);
+    let freed = DEALLOCATIONS.with(Cell::get);
+    let result = f();
+    let held = (ALLOCATIONS.with(Cell::get) - allocated) - (DEALLOCATIONS.with(Cell::get) - freed);
+    (result, held)
+}
+
+/// Bytes allocated by the current thread and not yet freed. Only
+/// differences between two readings are meaningful.
+pub(crate) fn live_bytes() -> isize {
+    LIVE_BYTES.with(Cell::get)
+}
+
+/// The most memory live at any one time while `f` ran, counted from what
+/// was live when it started.
+pub(crate) fn peak_bytes_during<F: FnOnce()>(f: F) -> usize {
+    let before = live_bytes();
+    PEAK_BYTES.with(|peak| peak.set(before));
+    f();
+    (PEAK_BYTES.with(Cell::get) - before) as usize
+}
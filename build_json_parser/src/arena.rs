@@ -0,0 +1,360 @@
+//! A compact, arena-backed alternative to `JsonValue`.
+//!
+//! Every node lives in one `Vec<Node>` and every string (keys included) in
+//! one shared text buffer. A container's children sit next to each other in
+//! the node vector, so a container is just an index range. Compared with
+//! `JsonValue`, which allocates a `Vec` per container and a `String` per
+//! string, a parsed document holds only two allocations. Parsing still
+//! allocates temporarily, e.g. for string tokens, but about half as often.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::error::ParseError;
+use crate::lexer::Token;
+use crate::pointer::{array_index, segments};
+use crate::stream::TokenStream;
+use crate::value::JsonValue;
+
+// Byte range into ArenaDocument::text
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Span {
+    start: usize,
+    end: usize,
+}
+
+impl Span {
+    const EMPTY: Span = Span { start: 0, end: 0 };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(Span),
+    // Children are nodes[start..start + len]
+    Array { start: usize, len: usize },
+    Object { start: usize, len: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Node {
+    // Only meaningful for object members
+    key: Span,
+    kind: Kind,
+}
+
+/// A parsed document stored in a single node arena.
+#[derive(Debug)]
+pub struct ArenaDocument {
+    nodes: Vec<Node>,
+    text: String,
+    root: usize,
+}
+
+impl ArenaDocument {
+    pub fn root(&self) -> NodeRef<'_> {
+        NodeRef { doc: self, index: self.root }
+    }
+
+    /// Number of nodes in the document, including the root.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn text(&self, span: Span) -> &str {
+        &self.text[span.start..span.end]
+    }
+}
+
+/// A borrowed view of one node, with a `JsonValue`-like read API.
+#[derive(Debug, Clone, Copy)]
+pub struct NodeRef<'a> {
+    doc: &'a ArenaDocument,
+    index: usize,
+}
+
+impl<'a> NodeRef<'a> {
+    fn node(&self) -> Node {
+        self.doc.nodes[self.index]
+    }
+
+    fn child(&self, index: usize) -> NodeRef<'a> {
+        NodeRef { doc: self.doc, index }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.node().kind == Kind::Null
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.node().kind {
+            Kind::Boolean(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.node().kind {
+            Kind::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self.node().kind {
+            Kind::String(span) => Some(self.doc.text(span)),
+            _ => None,
+        }
+    }
+
+    /// Number of elements or entries; `None` for scalars.
+    pub fn child_count(&self) -> Option<usize> {
+        match self.node().kind {
+            Kind::Array { len, .. } | Kind::Object { len, .. } => Some(len),
+            _ => None,
+        }
+    }
+
+    /// Array element at `index`.
+    pub fn at(&self, index: usize) -> Option<NodeRef<'a>> {
+        match self.node().kind {
+            Kind::Array { start, len } if index < len => Some(self.child(start + index)),
+            _ => None,
+        }
+    }
+
+    /// Object member named `key` (the last one if duplicated).
+    pub fn get(&self, key: &str) -> Option<NodeRef<'a>> {
+        match self.node().kind {
+            Kind::Object { start, len } => (start..start + len)
+                .rev()
+                .find(|&i| self.doc.text(self.doc.nodes[i].key) == key)
+                .map(|i| self.child(i)),
+            _ => None,
+        }
+    }
+
+    /// Object members in source order.
+    pub fn entries(&self) -> Vec<(&'a str, NodeRef<'a>)> {
+        match self.node().kind {
+            Kind::Object { start, len } => (start..start + len)
+                .map(|i| (self.doc.text(self.doc.nodes[i].key), self.child(i)))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Resolves a JSON Pointer relative to this node.
+    pub fn pointer(&self, ptr: &str) -> Option<NodeRef<'a>> {
+        let mut current = *self;
+        for segment in segments(ptr)? {
+            current = match current.node().kind {
+                Kind::Object { .. } => current.get(&segment)?,
+                Kind::Array { .. } => current.at(array_index(&segment)?)?,
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+
+    /// Copies this node (and everything below it) into a `JsonValue`.
+    pub fn to_value(&self) -> JsonValue {
+        match self.node().kind {
+            Kind::Null => JsonValue::Null,
+            Kind::Boolean(b) => JsonValue::Boolean(b),
+            Kind::Number(n) => JsonValue::Number(n),
+            Kind::String(span) => JsonValue::String(self.doc.text(span).to_string()),
+            Kind::Array { start, len } => JsonValue::Array((start..start + len).map(|i| self.child(i).to_value()).collect()),
+            Kind::Object { .. } => JsonValue::Object(
+                self.entries().into_iter().map(|(k, v)| (k.to_string(), v.to_value())).collect(),
+            ),
+        }
+    }
+}
+
+/// Parses `input` into an `ArenaDocument`.
+pub fn parse_arena(input: &str) -> Result<ArenaDocument, ParseError> {
+    let mut builder = Builder {
+        stream: TokenStream::new(input),
+        nodes: Vec::new(),
+        text: String::new(),
+        scratch: Vec::new(),
+    };
+    let root = builder.value(Span::EMPTY)?;
+    builder.stream.finish()?;
+    builder.nodes.push(root);
+
+    Ok(ArenaDocument {
+        root: builder.nodes.len() - 1,
+        nodes: builder.nodes,
+        text: builder.text,
+    })
+}
+
+struct Builder {
+    stream: TokenStream,
+    nodes: Vec<Node>,
+    text: String,
+    // Finished children of the containers currently being parsed. When a
+    // container closes, its children move from here into `nodes` as one
+    // contiguous run.
+    scratch: Vec<Node>,
+}
+
+impl Builder {
+    fn store_text(&mut self, s: &str) -> Span {
+        let start = self.text.len();
+        self.text.push_str(s);
+        Span { start, end: self.text.len() }
+    }
+
+    fn flush_children(&mut self, mark: usize) -> (usize, usize) {
+        let start = self.nodes.len();
+        self.nodes.extend(self.scratch.drain(mark..));
+        (start, self.nodes.len() - start)
+    }
+
+    fn value(&mut self, key: Span) -> Result<Node, ParseError> {
+        let kind = match self.stream.next()? {
+            Some((Token::LeftBrace, _)) => return self.object(key),
+            Some((Token::LeftBracket, _)) => return self.array(key),
+            Some((Token::String(s), _)) => Kind::String(self.store_text(&s)),
+            Some((Token::Number(n), _)) => Kind::Number(n),
+            Some((Token::Boolean(b), _)) => Kind::Boolean(b),
+            Some((Token::Null, _)) => Kind::Null,
+            Some((_, start)) => return Err(ParseError::at("Expected value", start)),
+            None => return Err(self.stream.error_at_end("Expected value")),
+        };
+        Ok(Node { key, kind })
+    }
+
+    fn array(&mut self, key: Span) -> Result<Node, ParseError> {
+        let mark = self.scratch.len();
+        if let Some((Token::RightBracket, _)) = self.stream.peek()? {
+            self.stream.next()?;
+        } else {
+            loop {
+                let element = self.value(Span::EMPTY)?;
+                self.scratch.push(element);
+                match self.stream.next()? {
+                    Some((Token::Comma, _)) => {
+                        if let Some((Token::RightBracket, start)) = self.stream.peek()? {
+                            return Err(ParseError::at("Trailing comma not allowed", *start));
+                        }
+                    }
+                    Some((Token::RightBracket, _)) => break,
+                    Some((_, start)) => return Err(ParseError::at("Expected ',' or ']'", start)),
+                    None => return Err(self.stream.error_at_end("Unexpected end of input")),
+                }
+            }
+        }
+        let (start, len) = self.flush_children(mark);
+        Ok(Node { key, kind: Kind::Array { start, len } })
+    }
+
+    fn object(&mut self, key: Span) -> Result<Node, ParseError> {
+        let mark = self.scratch.len();
+        if let Some((Token::RightBrace, _)) = self.stream.peek()? {
+            self.stream.next()?;
+        } else {
+            loop {
+                let member_key = match self.stream.next()? {
+                    Some((Token::String(s), _)) => self.store_text(&s),
+                    Some((_, start)) => return Err(ParseError::at("Expected string key", start)),
+                    None => return Err(self.stream.error_at_end("Unexpected end of input")),
+                };
+                match self.stream.next()? {
+                    Some((Token::Colon, _)) => {}
+                    Some((_, start)) => return Err(ParseError::at("Expected ':'", start)),
+                    None => return Err(self.stream.error_at_end("Expected ':'")),
+                }
+                let member = self.value(member_key)?;
+                self.scratch.push(member);
+                match self.stream.next()? {
+                    Some((Token::Comma, _)) => {
+                        if let Some((Token::RightBrace, start)) = self.stream.peek()? {
+                            return Err(ParseError::at("Trailing comma not allowed", *start));
+                        }
+                    }
+                    Some((Token::RightBrace, _)) => break,
+                    Some((_, start)) => return Err(ParseError::at("Expected ',' or '}'", start)),
+                    None => return Err(self.stream.error_at_end("Unexpected end of input")),
+                }
+            }
+        }
+        let (start, len) = self.flush_children(mark);
+        Ok(Node { key, kind: Kind::Object { start, len } })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc_counter::{allocations_during, retained_allocations};
+    use crate::parse;
+
+    const DOCUMENT: &str = r#"{
+        "name": "inventory",
+        "version": 3,
+        "items": [
+            {"sku": "a-1", "qty": 4, "tags": ["new", "sale"]},
+            {"sku": "b-2", "qty": 0, "tags": [], "discontinued": true}
+        ],
+        "owner": {"name": "ops", "contact": null}
+    }"#;
+
+    #[test]
+    fn test_read_back_by_path() {
+        let doc = parse_arena(DOCUMENT).unwrap();
+        let root = doc.root();
+
+        assert_eq!(root.get("name").unwrap().as_str(), Some("inventory"));
+        assert_eq!(root.pointer("/version").unwrap().as_f64(), Some(3.0));
+        assert_eq!(root.pointer("/items/0/tags/1").unwrap().as_str(), Some("sale"));
+        assert_eq!(root.pointer("/items/1/discontinued").unwrap().as_bool(), Some(true));
+        assert_eq!(root.pointer("/items/1/tags").unwrap().child_count(), Some(0));
+        assert!(root.pointer("/owner/contact").unwrap().is_null());
+        assert_eq!(root.pointer("/owner/name").unwrap().as_str(), Some("ops"));
+        assert!(root.pointer("/items/2").is_none());
+
+        let keys: Vec<&str> = root.entries().into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, vec!["name", "version", "items", "owner"]);
+    }
+
+    #[test]
+    fn test_matches_json_value() {
+        let doc = parse_arena(DOCUMENT).unwrap();
+        assert_eq!(doc.root().to_value(), parse(DOCUMENT).unwrap());
+        // 4 top-level members + 2 items with 3-4 members each + 2 tags + 2 owner members + root
+        assert_eq!(doc.node_count(), 4 + 2 + 7 + 2 + 2 + 1);
+    }
+
+    #[test]
+    fn test_allocates_less_than_json_value() {
+        let elements: Vec<String> = (0..2000).map(|i| format!(r#"{{"id": {}, "name": "user{}", "tags": ["a", "b"]}}"#, i, i)).collect();
+        let input = format!("[{}]", elements.join(","));
+
+        let arena = allocations_during(|| {
+            parse_arena(&input).unwrap();
+        });
+        let tree = allocations_during(|| {
+            parse(&input).unwrap();
+        });
+        assert!(arena * 3 < tree * 2, "arena: {}, tree: {}", arena, tree);
+
+        // Only the node vector and the text buffer outlive parsing
+        let (doc, held) = retained_allocations(|| parse_arena(&input).unwrap());
+        assert_eq!(held, 2);
+        assert_eq!(doc.root().child_count(), Some(2000));
+    }
+
+    #[test]
+    fn test_arena_errors() {
+        assert_eq!(parse_arena("[1, 2,]").unwrap_err().message(), "Trailing comma not allowed");
+        assert_eq!(parse_arena(r#"{"a" 1}"#).unwrap_err().message(), "Expected ':'");
+        assert_eq!(parse_arena(r#"{"a": 1"#).unwrap_err().message(), "Unexpected end of input");
+        assert_eq!(parse_arena("[] []").unwrap_err().message(), "Unexpected token after JSON value");
+    }
+}
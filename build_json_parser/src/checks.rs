@@ -0,0 +1,346 @@
+//! Checks run against an already-parsed document.
+
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::error::ParseError;
+use crate::pointer::PointerPath;
+use crate::value::{JsonValue, ValueKind};
+
+/// Checks that the root object has every key in `required`. Unless
+/// `allow_extra` is set, any other key is reported as unexpected.
+pub fn require_keys(value: &JsonValue, required: &[&str], allow_extra: bool) -> Result<(), ParseError> {
+    let entries = match value {
+        JsonValue::Object(entries) => entries,
+        _ => return Err(ParseError::Validation("Root must be an object".to_string())),
+    };
+
+    for key in required {
+        if !entries.iter().any(|(k, _)| k == key) {
+            return Err(ParseError::Validation(format!("Missing required key `{}`", key)));
+        }
+    }
+
+    if !allow_extra {
+        if let Some((key, _)) = entries.iter().find(|(k, _)| !required.contains(&k.as_str())) {
+            return Err(ParseError::Validation(format!("Unexpected key `{}`", key)));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the root value is one of the `allowed` kinds, e.g. only
+/// objects or arrays for a request body. The error names every allowed
+/// kind: "Root must be object or array".
+pub fn require_root(value: &JsonValue, allowed: &[ValueKind]) -> Result<(), ParseError> {
+    if allowed.contains(&value.kind()) {
+        return Ok(());
+    }
+    let names: Vec<&str> = allowed.iter().map(|kind| kind.name()).collect();
+    Err(ParseError::Validation(format!("Root must be {}", names.join(" or "))))
+}
+
+/// Maximum nesting depth of `value`: 0 for a scalar, 1 for a container
+/// (empty or not) holding only scalars, and one more for each level below.
+///
+/// Walks with an explicit stack, so very deep documents can't overflow it.
+pub fn depth(value: &JsonValue) -> usize {
+    let mut deepest = 0;
+    let mut pending = alloc::vec![(value, 0)];
+    while let Some((value, level)) = pending.pop() {
+        match value {
+            JsonValue::Array(elements) => {
+                deepest = deepest.max(level + 1);
+                pending.extend(elements.iter().map(|child| (child, level + 1)));
+            }
+            JsonValue::Object(entries) => {
+                deepest = deepest.max(level + 1);
+                pending.extend(entries.iter().map(|(_, child)| (child, level + 1)));
+            }
+            _ => {}
+        }
+    }
+    deepest
+}
+
+/// Every distinct object key found anywhere in `value`, at any depth and
+/// inside arrays, in sorted order.
+pub fn collect_keys(value: &JsonValue) -> BTreeSet<String> {
+    let mut keys = BTreeSet::new();
+    let mut pending = alloc::vec![value];
+    while let Some(value) = pending.pop() {
+        match value {
+            JsonValue::Array(elements) => pending.extend(elements),
+            JsonValue::Object(entries) => {
+                for (key, child) in entries {
+                    if !keys.contains(key) {
+                        keys.insert(key.clone());
+                    }
+                    pending.push(child);
+                }
+            }
+            _ => {}
+        }
+    }
+    keys
+}
+
+/// Whether every element of an array has the same kind, e.g. all numbers.
+/// Empty arrays count as homogeneous; non-arrays give `None`. Only the
+/// elements' own kinds are compared, so `[[1], ["a"]]` is homogeneous.
+pub fn is_homogeneous_array(value: &JsonValue) -> Option<bool> {
+    match value {
+        JsonValue::Array(elements) => Some(elements.windows(2).all(|pair| pair[0].kind() == pair[1].kind())),
+        _ => None,
+    }
+}
+
+/// Returns the JSON Pointers of every string value that starts or ends with
+/// whitespace, such as `"  padded "`, in document order. Object keys are
+/// not checked.
+pub fn find_whitespace_padded_strings(value: &JsonValue) -> Vec<String> {
+    let mut found = Vec::new();
+    collect_padded(value, &mut PointerPath::new(), &mut found);
+    found
+}
+
+fn collect_padded(value: &JsonValue, path: &mut PointerPath, found: &mut Vec<String>) {
+    match value {
+        JsonValue::String(s) if s.trim() != s => found.push(path.to_pointer()),
+        JsonValue::Array(elements) => {
+            for (i, element) in elements.iter().enumerate() {
+                path.with_index(i, |path| collect_padded(element, path, found));
+            }
+        }
+        JsonValue::Object(entries) => {
+            for (key, value) in entries {
+                path.with_key(key, |path| collect_padded(value, path, found));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Checks every string value against `f`, e.g. a length limit or "ASCII
+/// only". The first string, in document order, for which `f` returns false
+/// gives a validation error of `msg` and its pointer, such as "Name too
+/// long at /users/1/name" ("at the root" for a string root). Object keys
+/// are not checked.
+pub fn validate_strings<F: Fn(&str) -> bool>(value: &JsonValue, f: F, msg: &str) -> Result<(), ParseError> {
+    match find_failing_string(value, &f, &mut PointerPath::new()) {
+        Some(ptr) if ptr.is_empty() => Err(ParseError::Validation(format!("{} at the root", msg))),
+        Some(ptr) => Err(ParseError::Validation(format!("{} at {}", msg, ptr))),
+        None => Ok(()),
+    }
+}
+
+fn find_failing_string<F: Fn(&str) -> bool>(value: &JsonValue, f: &F, path: &mut PointerPath) -> Option<String> {
+    match value {
+        JsonValue::String(s) if !f(s) => Some(path.to_pointer()),
+        JsonValue::Array(elements) => elements
+            .iter()
+            .enumerate()
+            .find_map(|(i, element)| path.with_index(i, |path| find_failing_string(element, f, path))),
+        JsonValue::Object(entries) => {
+            entries.iter().find_map(|(key, value)| path.with_key(key, |path| find_failing_string(value, f, path)))
+        }
+        _ => None,
+    }
+}
+
+/// Finds the first object, in document order, whose keys aren't in
+/// lexicographic (byte) order. Returns its JSON Pointer and the first key
+/// that sorts before the key preceding it. Repeated keys next to each
+/// other count as sorted.
+pub fn find_unsorted_keys(value: &JsonValue) -> Option<(String, String)> {
+    find_unsorted_in(value, &mut PointerPath::new())
+}
+
+fn find_unsorted_in(value: &JsonValue, path: &mut PointerPath) -> Option<(String, String)> {
+    match value {
+        JsonValue::Array(elements) => {
+            elements.iter().enumerate().find_map(|(i, element)| path.with_index(i, |path| find_unsorted_in(element, path)))
+        }
+        JsonValue::Object(entries) => {
+            if let Some(pair) = entries.windows(2).find(|pair| pair[1].0 < pair[0].0) {
+                return Some((path.to_pointer(), pair[1].0.clone()));
+            }
+            entries.iter().find_map(|(key, value)| path.with_key(key, |path| find_unsorted_in(value, path)))
+        }
+        _ => None,
+    }
+}
+
+/// The JSON Pointer of the first place where `a` and `b` differ, or `None`
+/// if they are equal. Useful in assertion messages, where a full diff is
+/// more than needed.
+///
+/// Both documents are walked in `a`'s order: array elements by index, then
+/// object members by `a`'s keys, then any keys only `b` has. A value of a
+/// different kind counts as one difference at its own pointer, as does an
+/// element or member missing from either side. Key order doesn't matter,
+/// and numbers compare by value, so `1.0` equals `1`.
+pub fn first_difference(a: &JsonValue, b: &JsonValue) -> Option<String> {
+    first_difference_in(a, b, &mut PointerPath::new())
+}
+
+fn first_difference_in(a: &JsonValue, b: &JsonValue, path: &mut PointerPath) -> Option<String> {
+    match (a, b) {
+        (JsonValue::Array(left), JsonValue::Array(right)) => {
+            let found = left
+                .iter()
+                .zip(right)
+                .enumerate()
+                .find_map(|(i, (x, y))| path.with_index(i, |path| first_difference_in(x, y, path)));
+            let shorter = left.len().min(right.len());
+            found.or_else(|| (left.len() != right.len()).then(|| path.with_index(shorter, |path| path.to_pointer())))
+        }
+        (JsonValue::Object(left), JsonValue::Object(right)) => {
+            let found = left.iter().find_map(|(key, x)| {
+                path.with_key(key, |path| match right.iter().find(|(k, _)| k == key) {
+                    Some((_, y)) => first_difference_in(x, y, path),
+                    None => Some(path.to_pointer()),
+                })
+            });
+            found.or_else(|| {
+                right
+                    .iter()
+                    .find(|(key, _)| !left.iter().any(|(k, _)| k == key))
+                    .map(|(key, _)| path.with_key(key, |path| path.to_pointer()))
+            })
+        }
+        _ => (a != b).then(|| path.to_pointer()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_require_keys() {
+        let config = parse(r#"{"host": "localhost", "port": 8080}"#).unwrap();
+        assert!(require_keys(&config, &["host", "port"], false).is_ok());
+        assert!(require_keys(&config, &["host"], true).is_ok());
+    }
+
+    #[test]
+    fn test_missing_required_key() {
+        let config = parse(r#"{"host": "localhost"}"#).unwrap();
+        let err = require_keys(&config, &["host", "port"], true).unwrap_err();
+        assert_eq!(err, ParseError::Validation("Missing required key `port`".to_string()));
+    }
+
+    #[test]
+    fn test_unexpected_extra_key() {
+        let config = parse(r#"{"host": "localhost", "port": 8080, "debug": true}"#).unwrap();
+        let err = require_keys(&config, &["host", "port"], false).unwrap_err();
+        assert_eq!(err.message(), "Unexpected key `debug`");
+
+        let err = require_keys(&parse("[1]").unwrap(), &[], true).unwrap_err();
+        assert_eq!(err.message(), "Root must be an object");
+    }
+
+    #[test]
+    fn test_require_root_kinds() {
+        let allowed = [ValueKind::Object, ValueKind::Array];
+        assert!(require_root(&parse(r#"{"a": 1}"#).unwrap(), &allowed).is_ok());
+        assert!(require_root(&parse("[1, 2]").unwrap(), &allowed).is_ok());
+
+        let err = require_root(&parse("42").unwrap(), &allowed).unwrap_err();
+        assert_eq!(err, ParseError::Validation("Root must be object or array".to_string()));
+        let err = require_root(&parse(r#""string""#).unwrap(), &allowed).unwrap_err();
+        assert_eq!(err.message(), "Root must be object or array");
+    }
+
+    #[test]
+    fn test_find_whitespace_padded_strings() {
+        let doc = parse(r#"{"name": "  padded ", "city": "clean", "tags": ["ok", "tail "], "a/b": {" key ": " x"}}"#).unwrap();
+        assert_eq!(find_whitespace_padded_strings(&doc), vec!["/name", "/tags/1", "/a~1b/ key "]);
+        assert!(find_whitespace_padded_strings(&parse(r#"{"city": "clean", "n": 1}"#).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_find_unsorted_keys() {
+        let doc = parse(r#"{"a": 1, "b": [{"x": 1, "y": 2}, {"m": 1, "n": {"q": 1, "p": 2}, "k": 3}], "c": {"z": 1, "y": 2}}"#).unwrap();
+        // The object at /b/1 is reported before its own child /b/1/n
+        assert_eq!(find_unsorted_keys(&doc), Some(("/b/1".to_string(), "k".to_string())));
+
+        let root = parse(r#"{"b": 1, "a": {"d": 1, "c": 2}}"#).unwrap();
+        assert_eq!(find_unsorted_keys(&root), Some((String::new(), "a".to_string())));
+        assert_eq!(find_unsorted_keys(&parse(r#"{"B": 1, "a": [], "a": {"x": 1}}"#).unwrap()), None);
+        assert_eq!(find_unsorted_keys(&parse("[1, 2]").unwrap()), None);
+    }
+
+    #[test]
+    fn test_is_homogeneous_array() {
+        assert_eq!(is_homogeneous_array(&parse("[1, 2.5, -3]").unwrap()), Some(true));
+        assert_eq!(is_homogeneous_array(&parse(r#"[{"a": 1}, {"b": [true]}]"#).unwrap()), Some(true));
+        assert_eq!(is_homogeneous_array(&parse(r#"[1, "2", 3]"#).unwrap()), Some(false));
+        assert_eq!(is_homogeneous_array(&parse("[1, null]").unwrap()), Some(false));
+        assert_eq!(is_homogeneous_array(&parse("[]").unwrap()), Some(true));
+        assert_eq!(is_homogeneous_array(&parse(r#"{"a": [1]}"#).unwrap()), None);
+    }
+
+    #[test]
+    fn test_depth() {
+        assert_eq!(depth(&parse("42").unwrap()), 0);
+        assert_eq!(depth(&parse(r#"{"a": 1, "b": "x"}"#).unwrap()), 1);
+        assert_eq!(depth(&parse("[]").unwrap()), 1);
+        assert_eq!(depth(&parse(r#"{"a": {"b": 1}}"#).unwrap()), 2);
+        assert_eq!(depth(&parse(r#"[1, {"a": [[], {"b": [true]}]}, [2]]"#).unwrap()), 5);
+
+        let nested = format!("{}{}", "[".repeat(500), "]".repeat(500));
+        assert_eq!(depth(&parse(&nested).unwrap()), 500);
+    }
+
+    #[test]
+    fn test_collect_keys() {
+        let doc = parse(r#"{"users": [{"name": "ana", "tags": []}, {"name": "bo", "address": {"city": "x", "zip": 1}}], "meta": {"page": 1, "name": null}, "": 0}"#).unwrap();
+        let keys: Vec<String> = collect_keys(&doc).into_iter().collect();
+        assert_eq!(keys, vec!["", "address", "city", "meta", "name", "page", "tags", "users", "zip"]);
+        assert!(collect_keys(&parse("[1, [2, 3]]").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_first_difference_equal() {
+        let a = parse(r#"{"a": [1, {"b": null}], "c": "x"}"#).unwrap();
+        assert_eq!(first_difference(&a, &a.clone()), None);
+        // Key order and number spelling don't matter
+        let b = parse(r#"{"c": "x", "a": [1.0, {"b": null}]}"#).unwrap();
+        assert_eq!(first_difference(&a, &b), None);
+    }
+
+    #[test]
+    fn test_first_difference() {
+        let a = parse(r#"{"a": [1, {"b": null, "c": 2}], "d": "x"}"#).unwrap();
+        let differs = |b: &str| first_difference(&a, &parse(b).unwrap());
+        assert_eq!(differs(r#"{"a": [1, {"b": null, "c": 3}], "d": "y"}"#), Some("/a/1/c".to_string()));
+        assert_eq!(differs(r#"{"a": [1, {"b": false, "c": 2}], "d": "x"}"#), Some("/a/1/b".to_string()));
+        assert_eq!(differs(r#"{"a": [1], "d": "x"}"#), Some("/a/1".to_string()));
+        assert_eq!(differs(r#"{"a": [1, {"b": null, "c": 2}, 3], "d": "x"}"#), Some("/a/2".to_string()));
+        assert_eq!(differs(r#"{"a": [1, {"b": null, "c": 2}]}"#), Some("/d".to_string()));
+        assert_eq!(differs(r#"{"a": [1, {"b": null, "c": 2}], "d": "x", "e/f": 0}"#), Some("/e~1f".to_string()));
+        assert_eq!(differs(r#"{"a": {"0": 1}, "d": "x"}"#), Some("/a".to_string()));
+        assert_eq!(differs("[]"), Some(String::new()));
+    }
+
+    #[test]
+    fn test_validate_strings() {
+        let doc = parse(r#"{"users": [{"name": "ana", "bio": "short"}, {"name": "bartholomew", "bio": "a/b"}], "a/b": "x"}"#).unwrap();
+        let short = |s: &str| s.chars().count() <= 5;
+        let err = validate_strings(&doc, short, "String longer than 5 characters").unwrap_err();
+        assert_eq!(err, ParseError::Validation("String longer than 5 characters at /users/1/name".to_string()));
+
+        assert!(validate_strings(&doc, |s| s.len() <= 20, "Too long").is_ok());
+        // Keys aren't checked, but pointers escape them
+        let err = validate_strings(&doc, |s| s != "x", "Placeholder value").unwrap_err();
+        assert_eq!(err.message(), "Placeholder value at /a~1b");
+        let err = validate_strings(&parse(r#""café""#).unwrap(), |s| s.is_ascii(), "Not ASCII").unwrap_err();
+        assert_eq!(err.message(), "Not ASCII at the root");
+        assert!(validate_strings(&parse("[1, true, null]").unwrap(), |_| false, "Never").is_ok());
+    }
+}
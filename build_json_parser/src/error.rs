@@ -0,0 +1,63 @@
+use alloc::string::{String, ToString};
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::lexer::Position;
+
+/// Why a document was rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The input is not acceptable JSON under the active options. The
+    /// position is known for errors found while lexing or parsing.
+    Syntax { message: String, position: Option<Position> },
+    /// The document parsed but failed a check on its contents.
+    Validation(String),
+    /// Reading the input failed (including input that isn't valid UTF-8).
+    Io(String),
+}
+
+impl ParseError {
+    pub fn at(message: &str, position: Position) -> Self {
+        ParseError::Syntax { message: message.to_string(), position: Some(position) }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ParseError::Syntax { message, .. } => message,
+            ParseError::Validation(message) => message,
+            ParseError::Io(message) => message,
+        }
+    }
+
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            ParseError::Syntax { position, .. } => *position,
+            ParseError::Validation(_) | ParseError::Io(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position() {
+            Some(position) => write!(f, "{} at line {}, column {}", self.message(), position.line, position.column),
+            None => f.write_str(self.message()),
+        }
+    }
+}
+
+impl core::error::Error for ParseError {}
+
+impl From<&'static str> for ParseError {
+    fn from(message: &'static str) -> Self {
+        ParseError::Syntax { message: message.to_string(), position: None }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for ParseError {
+    fn from(err: io::Error) -> Self {
+        ParseError::Io(err.to_string())
+    }
+}
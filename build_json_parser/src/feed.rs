@@ -0,0 +1,202 @@
+//! Push-style parsing of input that arrives in chunks.
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use crate::error::ParseError;
+use crate::value::JsonValue;
+
+/// What `FeedParser::feed` produced from the bytes seen so far.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeedResult {
+    /// No complete value yet; feed more bytes.
+    NeedMore,
+    /// A value is complete. Bytes after it stay buffered for the next one.
+    Complete(JsonValue),
+    /// The buffered value is malformed.
+    Error(ParseError),
+}
+
+/// Parses JSON values from bytes fed in arbitrary chunks, e.g. as they
+/// arrive over a socket.
+///
+/// This is a separate type rather than a `Parser::feed` method because
+/// `Parser` works on the complete token list of a finished input and can't
+/// be resumed part way through a token.
+///
+/// **One chunk may hold several values.** `feed` returns only the first;
+/// the rest stay buffered until asked for. Unless `feed` returned
+/// `NeedMore`, keep calling `next_value` until it does before feeding the
+/// next chunk.
+///
+/// Incoming bytes are scanned once to find where the value ends (tracking
+/// bracket depth and string state), so tokens and UTF-8 characters may be
+/// split across feeds. The value is parsed when its end is seen; syntax
+/// errors are reported at that point.
+///
+/// A top-level scalar such as `42` only ends at the next whitespace or
+/// delimiter, so at the end of the stream call `finish` to parse it.
+#[derive(Debug, Default)]
+pub struct FeedParser {
+    buffer: Vec<u8>,
+    scanned: usize,
+    depth: usize,
+    started: bool,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl FeedParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `bytes` to the buffer and returns the next complete value, if
+    /// there is one. Any further values already buffered are returned by
+    /// `next_value`.
+    pub fn feed(&mut self, bytes: &[u8]) -> FeedResult {
+        self.buffer.extend_from_slice(bytes);
+        self.next_value()
+    }
+
+    /// Returns the next complete value from bytes already fed, or
+    /// `NeedMore` once none is left.
+    pub fn next_value(&mut self) -> FeedResult {
+        match self.scan() {
+            Some(end) => self.complete(end),
+            None => FeedResult::NeedMore,
+        }
+    }
+
+    /// Parses whatever is buffered at the end of the stream. Returns
+    /// `NeedMore` if only whitespace is left.
+    pub fn finish(&mut self) -> FeedResult {
+        if self.buffer.iter().all(|b| b.is_ascii_whitespace()) {
+            self.buffer.clear();
+            self.reset();
+            return FeedResult::NeedMore;
+        }
+        self.complete(self.buffer.len())
+    }
+
+    // Scans the unscanned bytes, returning the end of the value if it
+    // has been reached
+    fn scan(&mut self) -> Option<usize> {
+        while self.scanned < self.buffer.len() {
+            let i = self.scanned;
+            let byte = self.buffer[i];
+            self.scanned += 1;
+
+            if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if byte == b'\\' {
+                    self.escaped = true;
+                } else if byte == b'"' {
+                    self.in_string = false;
+                    if self.depth == 0 {
+                        return Some(i + 1);
+                    }
+                }
+                continue;
+            }
+
+            match byte {
+                b'"' => {
+                    self.in_string = true;
+                    self.started = true;
+                }
+                b'{' | b'[' => {
+                    self.depth += 1;
+                    self.started = true;
+                }
+                b'}' | b']' if self.depth > 1 => self.depth -= 1,
+                // Closes the value, or is stray and left for the parser to reject
+                b'}' | b']' => return Some(i + 1),
+                // The end of a top-level number or literal
+                b',' | b':' | b' ' | b'\t' | b'\n' | b'\r' if self.started && self.depth == 0 => return Some(i),
+                b' ' | b'\t' | b'\n' | b'\r' => {}
+                _ => self.started = true,
+            }
+        }
+        None
+    }
+
+    // Parses buffer[..end], keeping the rest for the next value
+    fn complete(&mut self, end: usize) -> FeedResult {
+        let rest = self.buffer.split_off(end);
+        let value = core::mem::replace(&mut self.buffer, rest);
+        self.reset();
+
+        match core::str::from_utf8(&value) {
+            Ok(text) => match crate::parse(text) {
+                Ok(value) => FeedResult::Complete(value),
+                Err(e) => FeedResult::Error(e),
+            },
+            Err(_) => FeedResult::Error(ParseError::Io("stream did not contain valid UTF-8".to_string())),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.scanned = 0;
+        self.depth = 0;
+        self.started = false;
+        self.in_string = false;
+        self.escaped = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_feed_one_byte_at_a_time() {
+        let text = r#"{"name": "café ☕", "n": [12345, -6.5e3, true, null], "s": "a}b]"}"#;
+        let mut parser = FeedParser::new();
+        let bytes = text.as_bytes();
+
+        for &byte in &bytes[..bytes.len() - 1] {
+            assert_eq!(parser.feed(&[byte]), FeedResult::NeedMore);
+        }
+        assert_eq!(parser.feed(&bytes[bytes.len() - 1..]), FeedResult::Complete(parse(text).unwrap()));
+        assert_eq!(parser.finish(), FeedResult::NeedMore);
+    }
+
+    #[test]
+    fn test_tokens_split_across_feeds() {
+        let mut parser = FeedParser::new();
+        assert_eq!(parser.feed(b"[123"), FeedResult::NeedMore);
+        assert_eq!(parser.feed(b"45, \"ab"), FeedResult::NeedMore);
+        assert_eq!(parser.feed(b"cd\"]"), FeedResult::Complete(parse(r#"[12345, "abcd"]"#).unwrap()));
+
+        // A top-level number isn't complete until something follows it
+        assert_eq!(parser.feed(b"4"), FeedResult::NeedMore);
+        assert_eq!(parser.feed(b"2"), FeedResult::NeedMore);
+        assert_eq!(parser.finish(), FeedResult::Complete(JsonValue::Number(42.0)));
+    }
+
+    #[test]
+    fn test_consecutive_values_and_errors() {
+        let mut parser = FeedParser::new();
+        assert_eq!(parser.feed(b"{} [1"), FeedResult::Complete(JsonValue::Object(Vec::new())));
+        assert_eq!(parser.feed(b"]"), FeedResult::Complete(parse("[1]").unwrap()));
+
+        // Two values in one chunk: the second is waiting for next_value
+        assert_eq!(parser.feed(b"{}{\"a\": 1}\n[2"), FeedResult::Complete(JsonValue::Object(Vec::new())));
+        assert_eq!(parser.next_value(), FeedResult::Complete(parse(r#"{"a": 1}"#).unwrap()));
+        assert_eq!(parser.next_value(), FeedResult::NeedMore);
+        assert_eq!(parser.feed(b"]"), FeedResult::Complete(parse("[2]").unwrap()));
+
+        match parser.feed(b"[1,,2]") {
+            FeedResult::Error(e) => assert_eq!(e.message(), "Expected value"),
+            other => panic!("expected an error, got {:?}", other),
+        }
+        assert_eq!(parser.feed(b"[1, 2"), FeedResult::NeedMore);
+        match parser.finish() {
+            FeedResult::Error(e) => assert_eq!(e.message(), "Unexpected end of input"),
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,609 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::error::ParseError;
+use crate::options::ParseOptions;
+
+// Token Definition
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    LeftBrace,       // Represents {
+    RightBrace,      // Represents }
+    LeftBracket,     // Represents [
+    RightBracket,    // Represents ]
+    String(String),  // Represents any string value (both keys and values)
+    Number(f64),     // Represents any number value
+    Boolean(bool),   // Represents any boolean value
+    Null,
+    Colon,           // Represents :
+    Comma,           // Represents ,
+}
+
+/// A location in the source text. Lines and columns are 1-based and count
+/// characters; `offset` is the 0-based byte offset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+impl Position {
+    fn start() -> Self {
+        Position { line: 1, column: 1, offset: 0 }
+    }
+}
+
+// U+FDD0..U+FDEF and U+xFFFE/U+xFFFF in every plane
+fn is_noncharacter(c: char) -> bool {
+    let code = c as u32;
+    (0xFDD0..=0xFDEF).contains(&code) || code & 0xFFFE == 0xFFFE
+}
+
+// How a token reads in trace output, e.g. "string 'key'" or "','"
+pub(crate) fn describe(token: &Token) -> String {
+    match token {
+        Token::LeftBrace => "'{'".to_string(),
+        Token::RightBrace => "'}'".to_string(),
+        Token::LeftBracket => "'['".to_string(),
+        Token::RightBracket => "']'".to_string(),
+        Token::Colon => "':'".to_string(),
+        Token::Comma => "','".to_string(),
+        Token::String(s) => format!("string '{}'", s),
+        Token::Number(n) => format!("number {}", n),
+        Token::Boolean(b) => b.to_string(),
+        Token::Null => "null".to_string(),
+    }
+}
+
+/// A comment found while lexing with `allow_comments` and
+/// `collect_comments` enabled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// The text between the delimiters (`//` and end of line, or `/*`
+    /// and `*/`), untrimmed.
+    pub text: String,
+    /// Where the comment's opening delimiter starts.
+    pub position: Position,
+    /// True for `/* */` comments, false for `//` comments.
+    pub block: bool,
+}
+
+#[derive(Debug)]
+pub struct Lexer {
+    input: Vec<char>,
+    position: usize,
+    location: Position,
+    options: ParseOptions,
+    comments: Vec<Comment>,
+    trace: Vec<String>,
+}
+
+impl Lexer {
+    pub fn new(input: &str) -> Self {
+        Self::with_options(input, ParseOptions::default())
+    }
+
+    pub fn with_options(input: &str, options: ParseOptions) -> Self {
+        Lexer {
+            input: input.chars().collect(),
+            position: 0,
+            location: Position::start(),
+            options,
+            comments: Vec::new(),
+            trace: Vec::new(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input.get(self.position).copied()
+    }
+
+    fn peek_ahead(&self, offset: usize) -> Option<char> {
+        self.input.get(self.position + offset).copied()
+    }
+
+    fn advance(&mut self) {
+        if let Some(c) = self.peek() {
+            self.location.offset += c.len_utf8();
+            if c == '\n' {
+                self.location.line += 1;
+                self.location.column = 1;
+            } else {
+                self.location.column += 1;
+            }
+        }
+        self.position += 1
+    }
+
+    fn read_while<F>(&mut self, predicate: F) -> String
+    where F: Fn(char) -> bool {
+        let mut result = String::new();
+        while let Some(c) = self.peek() {
+            if predicate(c) {
+                result.push(c);
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        result
+    }
+
+    fn lex_string(&mut self) -> Result<String, &'static str> {
+        let mut result = String::new();
+        self.advance(); // Skip opening quote
+
+        while let Some(c) = self.peek() {
+            match c {
+                '"' => {
+                    self.advance();
+                    return Ok(result);
+                }
+                '\n' => return Err("Unterminated string literal"),
+                c => {
+                    self.advance();
+                    let c = if c == '\\' { self.lex_escape()? } else { c };
+                    if self.options.reject_noncharacters && is_noncharacter(c) {
+                        return Err("Noncharacter in string");
+                    }
+                    if self.options.max_string_bytes.is_some_and(|max| result.len() + c.len_utf8() > max) {
+                        return Err("String exceeds byte limit");
+                    }
+                    result.push(c);
+                }
+            }
+        }
+        Err("Unterminated string literal")
+    }
+
+    // Decodes the escape sequence after a backslash. A \u escape for a
+    // high surrogate must be followed by one for a low surrogate, and the
+    // pair becomes a single character.
+    fn lex_escape(&mut self) -> Result<char, &'static str> {
+        let c = self.peek().ok_or("Unterminated string literal")?;
+        self.advance();
+        let decoded = match c {
+            '"' => '"',
+            '\\' => '\\',
+            '/' => '/',
+            'b' => '\u{08}',
+            'f' => '\u{0c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let mut code = self.lex_hex4()?;
+                if (0xD800..0xDC00).contains(&code) {
+                    if self.peek() != Some('\\') || self.peek_ahead(1) != Some('u') {
+                        return Err("Invalid unicode escape");
+                    }
+                    self.advance();
+                    self.advance();
+                    let low = self.lex_hex4()?;
+                    if !(0xDC00..0xE000).contains(&low) {
+                        return Err("Invalid unicode escape");
+                    }
+                    code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                }
+                // Lone low surrogates aren't characters
+                char::from_u32(code).ok_or("Invalid unicode escape")?
+            }
+            _ => return Err("Invalid escape sequence"),
+        };
+        Ok(decoded)
+    }
+
+    // The four hex digits of a \u escape
+    fn lex_hex4(&mut self) -> Result<u32, &'static str> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self.peek().and_then(|c| c.to_digit(16)).ok_or("Invalid unicode escape")?;
+            code = code * 16 + digit;
+            self.advance();
+        }
+        Ok(code)
+    }
+
+    // Skips a // or /* */ comment, keeping it if collect_comments is set
+    fn lex_comment(&mut self) -> Result<(), &'static str> {
+        let start = self.location;
+        let block = match self.peek_ahead(1) {
+            Some('/') => false,
+            Some('*') => true,
+            _ => return Err("Invalid character in JSON"),
+        };
+        self.advance();
+        self.advance();
+
+        let text = if block {
+            let mut text = String::new();
+            loop {
+                match self.peek() {
+                    Some('*') if self.peek_ahead(1) == Some('/') => {
+                        self.advance();
+                        self.advance();
+                        break;
+                    }
+                    Some(c) => {
+                        text.push(c);
+                        self.advance();
+                    }
+                    None => return Err("Unterminated comment"),
+                }
+            }
+            text
+        } else {
+            self.read_while(|c| c != '\n')
+        };
+
+        if self.options.collect_comments {
+            self.comments.push(Comment { text, position: start, block });
+        }
+        Ok(())
+    }
+
+    fn lex_number(&mut self) -> Result<f64, &'static str> {
+        if self.options.allow_radix_prefixes {
+            if let Some(number) = self.lex_radix_integer()? {
+                return Ok(number);
+            }
+        }
+
+        let number_str = self.read_while(|c| {
+            c.is_ascii_digit() || c == '-' || c == '.' || c == 'e' || c == 'E' || c == '+'
+        });
+
+        check_number_grammar(&number_str)?;
+        let number = number_str.parse::<f64>()
+            .map_err(|_| "Invalid number format")?;
+
+        if (self.options.canonicalize_numbers || self.options.normalize_on_parse) && number == 0.0 {
+            return Ok(0.0);
+        }
+        Ok(number)
+    }
+
+    // Lexes 0x1F, 0b1010 and 0o17 style integers (optionally negated).
+    // Returns None without consuming anything when there is no prefix.
+    fn lex_radix_integer(&mut self) -> Result<Option<f64>, &'static str> {
+        let sign_len = if self.peek() == Some('-') { 1 } else { 0 };
+        if self.peek_ahead(sign_len) != Some('0') {
+            return Ok(None);
+        }
+        let radix = match self.peek_ahead(sign_len + 1) {
+            Some('x') | Some('X') => 16,
+            Some('b') | Some('B') => 2,
+            Some('o') | Some('O') => 8,
+            _ => return Ok(None),
+        };
+
+        for _ in 0..sign_len + 2 {
+            self.advance();
+        }
+        let digits = self.read_while(|c| c.is_ascii_alphanumeric());
+        let magnitude = i64::from_str_radix(&digits, radix)
+            .map_err(|_| "Invalid number format")?;
+
+        Ok(Some(if sign_len == 1 { -magnitude } else { magnitude } as f64))
+    }
+
+    fn lex_identifier(&mut self) -> Result<Token, &'static str> {
+        let identifier = self.read_while(|c| c.is_ascii_alphabetic());
+
+        match identifier.as_str() {
+            "true" => Ok(Token::Boolean(true)),
+            "false" => Ok(Token::Boolean(false)),
+            "null" => Ok(Token::Null),
+            _ => Err("Invalid identifier")
+        }
+    }
+
+    // Main lexing function that produces tokens
+    pub fn lex_tokens(&mut self) -> Result<Vec<Token>, ParseError> {
+        self.lex_with_positions().map(|(tokens, _)| tokens)
+    }
+
+    // Like lex_tokens, but also returns where each token starts. The
+    // positions vector has one extra entry marking the end of input.
+    pub fn lex_with_positions(&mut self) -> Result<(Vec<Token>, Vec<Position>), ParseError> {
+        let mut tokens = Vec::new();
+        let mut positions = Vec::new();
+
+        while let Some((token, start)) = self.next_token()? {
+            tokens.push(token);
+            positions.push(start);
+        }
+        positions.push(self.location);
+        Ok((tokens, positions))
+    }
+
+    // Lexes a single token on demand, skipping whitespace before it.
+    // Returns Ok(None) at the end of input.
+    pub fn next_token(&mut self) -> Result<Option<(Token, Position)>, ParseError> {
+        while let Some(c) = self.peek() {
+            let start = self.location;
+            let token = match c {
+                '{' => {
+                    self.advance();
+                    Token::LeftBrace
+                },
+                '}' => {
+                    self.advance();
+                    Token::RightBrace
+                },
+                '[' => {
+                    self.advance();
+                    Token::LeftBracket
+                },
+                ']' => {
+                    self.advance();
+                    Token::RightBracket
+                },
+                ':' => {
+                    self.advance();
+                    Token::Colon
+                },
+                ',' => {
+                    self.advance();
+                    Token::Comma
+                },
+                '"' => {
+                    let string = self.lex_string().map_err(|e| ParseError::at(e, start))?;
+                    Token::String(string)
+                },
+                c if c.is_ascii_digit() || c == '-' => {
+                    let number = self.lex_number().map_err(|e| ParseError::at(e, start))?;
+                    Token::Number(number)
+                },
+                c if c.is_ascii_alphabetic() => {
+                    self.lex_identifier().map_err(|e| ParseError::at(e, start))?
+                }
+                c if c.is_whitespace() => {
+                    self.advance();
+                    continue;
+                },
+                '/' if self.options.allow_comments => {
+                    self.lex_comment().map_err(|e| ParseError::at(e, start))?;
+                    continue;
+                },
+                _ => return Err(ParseError::at("Invalid character in JSON", start)),
+            };
+            if self.options.trace {
+                self.trace.push(format!("lex: read {} at {}:{}", describe(&token), start.line, start.column));
+            }
+            return Ok(Some((token, start)));
+        }
+        Ok(None)
+    }
+
+    // Hands over the comments collected so far
+    pub fn take_comments(&mut self) -> Vec<Comment> {
+        core::mem::take(&mut self.comments)
+    }
+
+    // Hands over the trace lines recorded so far (see ParseOptions::trace)
+    pub fn take_trace(&mut self) -> Vec<String> {
+        core::mem::take(&mut self.trace)
+    }
+
+    // Steps over one character, e.g. to get past an invalid one
+    pub(crate) fn skip_char(&mut self) {
+        self.advance();
+    }
+
+    // Where the lexer currently is in the input
+    pub fn location(&self) -> Position {
+        self.location
+    }
+
+    /// How much of the input has been lexed, from 0.0 to 1.0, counted in
+    /// characters. Empty input is fully lexed from the start.
+    pub fn progress(&self) -> f64 {
+        if self.input.is_empty() {
+            1.0
+        } else {
+            self.position as f64 / self.input.len() as f64
+        }
+    }
+}
+
+// Checks `text` against the RFC 8259 number grammar:
+// -? (0 | [1-9][0-9]*) (.[0-9]+)? ([eE][+-]?[0-9]+)?
+// The integer part can't have leading zeros ("01", "-00"), but the
+// exponent can ("1e05").
+fn check_number_grammar(text: &str) -> Result<(), &'static str> {
+    let bytes = text.as_bytes();
+    let mut i = usize::from(bytes.first() == Some(&b'-'));
+    let digits = |i: usize| bytes[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+
+    let integer = digits(i);
+    if integer == 0 {
+        return Err("Invalid number format");
+    }
+    if integer > 1 && bytes[i] == b'0' {
+        return Err("Leading zeros not allowed");
+    }
+    i += integer;
+
+    if bytes.get(i) == Some(&b'.') {
+        let fraction = digits(i + 1);
+        if fraction == 0 {
+            return Err("Invalid number format");
+        }
+        i += 1 + fraction;
+    }
+    if matches!(bytes.get(i), Some(b'e' | b'E')) {
+        i += 1;
+        if matches!(bytes.get(i), Some(b'+' | b'-')) {
+            i += 1;
+        }
+        let exponent = digits(i);
+        if exponent == 0 {
+            return Err("Invalid number format");
+        }
+        i += exponent;
+    }
+    if i != bytes.len() {
+        return Err("Invalid number format");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lex_lenient(input: &str) -> Result<Vec<Token>, ParseError> {
+        let options = ParseOptions { allow_radix_prefixes: true, ..ParseOptions::default() };
+        Lexer::with_options(input, options).lex_tokens()
+    }
+
+    #[test]
+    fn test_radix_prefixes_in_lenient_mode() {
+        assert_eq!(lex_lenient("0x1F").unwrap(), vec![Token::Number(31.0)]);
+        assert_eq!(lex_lenient("0b1010").unwrap(), vec![Token::Number(10.0)]);
+        assert_eq!(lex_lenient("0o17").unwrap(), vec![Token::Number(15.0)]);
+        assert_eq!(lex_lenient("-0xff").unwrap(), vec![Token::Number(-255.0)]);
+        assert_eq!(lex_lenient("[0, 0.5]").unwrap(), vec![
+            Token::LeftBracket, Token::Number(0.0), Token::Comma, Token::Number(0.5), Token::RightBracket,
+        ]);
+    }
+
+    #[test]
+    fn test_invalid_radix_digits() {
+        assert_eq!(lex_lenient("0b102").unwrap_err().message(), "Invalid number format");
+        assert_eq!(lex_lenient("0x").unwrap_err().message(), "Invalid number format");
+    }
+
+    #[test]
+    fn test_radix_prefixes_rejected_in_strict_mode() {
+        assert!(Lexer::new("0x1F").lex_tokens().is_err());
+        assert!(Lexer::new("0b1010").lex_tokens().is_err());
+        assert!(Lexer::new("0o17").lex_tokens().is_err());
+    }
+
+    #[test]
+    fn test_token_positions() {
+        let (tokens, positions) = Lexer::new("{\n  \"é\": 12\n}").lex_with_positions().unwrap();
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(positions, vec![
+            Position { line: 1, column: 1, offset: 0 },
+            Position { line: 2, column: 3, offset: 4 },
+            Position { line: 2, column: 6, offset: 8 },
+            Position { line: 2, column: 8, offset: 10 },
+            Position { line: 3, column: 1, offset: 13 },
+            Position { line: 3, column: 2, offset: 14 },
+        ]);
+    }
+
+    #[test]
+    fn test_error_position_is_token_start() {
+        let err = Lexer::new("[1,\n 12.34.56]").lex_tokens().unwrap_err();
+        assert_eq!(err.message(), "Invalid number format");
+        assert_eq!(err.position(), Some(Position { line: 2, column: 2, offset: 5 }));
+    }
+
+    #[test]
+    fn test_comments_rejected_by_default() {
+        let err = Lexer::new("// hi\n{}").lex_tokens().unwrap_err();
+        assert_eq!(err.message(), "Invalid character in JSON");
+    }
+
+    #[test]
+    fn test_comments_skipped() {
+        let options = ParseOptions { allow_comments: true, ..ParseOptions::default() };
+        let tokens = Lexer::with_options("[1, // one\n /* two */ 2]", options).lex_tokens().unwrap();
+        assert_eq!(tokens, vec![
+            Token::LeftBracket, Token::Number(1.0), Token::Comma, Token::Number(2.0), Token::RightBracket,
+        ]);
+
+        let options = ParseOptions { allow_comments: true, ..ParseOptions::default() };
+        let err = Lexer::with_options("[1 /* open", options).lex_tokens().unwrap_err();
+        assert_eq!(err.message(), "Unterminated comment");
+    }
+
+    #[test]
+    fn test_collect_comments() {
+        let options = ParseOptions { allow_comments: true, collect_comments: true, ..ParseOptions::default() };
+        let mut lexer = Lexer::with_options("{\n  // port to bind\n  \"port\": 80 /* default */\n}", options);
+        lexer.lex_tokens().unwrap();
+        assert_eq!(lexer.take_comments(), vec![
+            Comment {
+                text: " port to bind".to_string(),
+                position: Position { line: 2, column: 3, offset: 4 },
+                block: false,
+            },
+            Comment {
+                text: " default ".to_string(),
+                position: Position { line: 3, column: 14, offset: 33 },
+                block: true,
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_max_string_bytes() {
+        let options = ParseOptions { max_string_bytes: Some(8), ..ParseOptions::default() };
+        let tokens = Lexer::with_options("\"abcdefgh\"", options.clone()).lex_tokens().unwrap();
+        assert_eq!(tokens, vec![Token::String("abcdefgh".to_string())]);
+
+        // Five chars, but ten bytes in UTF-8
+        let err = Lexer::with_options("[\"ééééé\"]", options).lex_tokens().unwrap_err();
+        assert_eq!(err.message(), "String exceeds byte limit");
+        assert_eq!(err.position(), Some(Position { line: 1, column: 2, offset: 1 }));
+    }
+
+    fn lex_string_token(input: &str, options: ParseOptions) -> Result<Token, ParseError> {
+        let mut tokens = Lexer::with_options(input, options).lex_tokens()?;
+        assert_eq!(tokens.len(), 1);
+        Ok(tokens.remove(0))
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let lex = |input: &str| lex_string_token(input, ParseOptions::default());
+        assert_eq!(lex(r#""a\"b\\c\/d\b\f\n\r\t""#).unwrap(), Token::String("a\"b\\c/d\u{8}\u{c}\n\r\t".into()));
+        assert_eq!(lex(r#""caf\u00e9 \u00E9""#).unwrap(), Token::String("café é".into()));
+        assert_eq!(lex(r#""\ud83d\ude00""#).unwrap(), Token::String("\u{1F600}".into()));
+
+        assert_eq!(lex(r#""\x""#).unwrap_err().message(), "Invalid escape sequence");
+        assert_eq!(lex(r#""\u12g4""#).unwrap_err().message(), "Invalid unicode escape");
+        assert_eq!(lex(r#""\ud83d""#).unwrap_err().message(), "Invalid unicode escape");
+        assert_eq!(lex(r#""\ude00""#).unwrap_err().message(), "Invalid unicode escape");
+        assert_eq!(lex(r#""\"#).unwrap_err().message(), "Unterminated string literal");
+    }
+
+    #[test]
+    fn test_reject_noncharacters() {
+        let strict = ParseOptions { reject_noncharacters: true, ..ParseOptions::default() };
+        assert_eq!(lex_string_token(r#""\uFFFF""#, ParseOptions::default()).unwrap(), Token::String("\u{FFFF}".into()));
+
+        let err = lex_string_token(r#""ok \uffff""#, strict.clone()).unwrap_err();
+        assert_eq!(err.message(), "Noncharacter in string");
+        assert!(lex_string_token(r#""\uFDD0""#, strict.clone()).is_err());
+        // U+10FFFE, the noncharacter at the end of the last plane
+        assert!(lex_string_token(r#""\uDBFF\uDFFE""#, strict.clone()).is_err());
+        assert!(lex_string_token("\"\u{1FFFF}\"", strict.clone()).is_err());
+        assert!(lex_string_token(r#""\uFFFD \uFDCF""#, strict).is_ok());
+    }
+
+    #[test]
+    fn test_number_grammar() {
+        let number = |input: &str| Lexer::new(input).lex_tokens().map_err(|e| e.message().to_string());
+        assert_eq!(number("1e05").unwrap(), vec![Token::Number(1e5)]);
+        assert_eq!(number("0.0e0").unwrap(), vec![Token::Number(0.0)]);
+        for valid in ["0", "-0", "0.5", "0e1", "10", "-12.5E+3", "1E-07", "0.000"] {
+            assert!(number(valid).is_ok(), "{} should lex", valid);
+        }
+
+        assert_eq!(number("01").unwrap_err(), "Leading zeros not allowed");
+        assert_eq!(number("00").unwrap_err(), "Leading zeros not allowed");
+        assert_eq!(number("-01").unwrap_err(), "Leading zeros not allowed");
+        assert_eq!(number("-00.5").unwrap_err(), "Leading zeros not allowed");
+        for invalid in ["-", "1.", "1.e3", "1e", "1e+", "-.5", "1-2", "1e5.0", "1+"] {
+            assert_eq!(number(invalid).unwrap_err(), "Invalid number format", "{} should be rejected", invalid);
+        }
+    }
+}
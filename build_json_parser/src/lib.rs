@@ -0,0 +1,471 @@
+//! A small JSON lexer, parser and value model.
+//!
+//! The library is `no_std` and only needs `alloc`. The default `std`
+//! feature adds I/O entry points such as `from_reader`; the `json_parser`
+//! binary requires it.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod access;
+#[cfg(test)]
+mod alloc_counter;
+mod arena;
+mod checks;
+mod error;
+mod feed;
+mod lexer;
+mod options;
+mod parser;
+mod patch;
+mod pointer;
+mod recover;
+mod repair;
+mod serialize;
+mod shared;
+mod spans;
+mod stream;
+mod transform;
+mod value;
+mod warnings;
+#[cfg(feature = "serde")]
+mod serde_bridge;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::Read;
+
+use stream::TokenStream;
+
+pub use access::{get_array, get_bool, get_i64, get_string, number_in_range};
+pub use arena::{parse_arena, ArenaDocument, NodeRef};
+pub use checks::{collect_keys, depth, find_unsorted_keys, first_difference, find_whitespace_padded_strings, is_homogeneous_array, require_keys, require_root, validate_strings};
+pub use error::ParseError;
+pub use feed::{FeedParser, FeedResult};
+pub use lexer::{Comment, Lexer, Position, Token};
+pub use options::{CommaPolicy, DuplicateKeyPolicy, FormatOptions, ParseOptions};
+pub use parser::{ParseMode, Parser};
+pub use patch::apply_patch;
+pub use pointer::{pointer, pointer_mut, set};
+pub use recover::parse_collecting;
+pub use repair::repair;
+pub use serialize::{canonical_hash, serialized_len, sort_keys, to_string, to_string_pretty, to_string_with};
+pub use shared::{parse_shared, SharedValue};
+pub use spans::{parse_with_spans, Spanned, SpannedValue};
+pub use stream::for_each_array_element;
+pub use transform::{apply_defaults, apply_defaults_with, flatten, flatten_with, merge, merge_with, sort_array_by_key, truncate_depth, unflatten, ArrayMerge, Defaults};
+pub use value::{JsonValue, ValueKind};
+pub use warnings::{parse_with_warnings, Warning, DEEP_NESTING};
+
+/// Parses a complete JSON document into a `JsonValue`.
+///
+/// Any value is accepted at the top level, but nothing may follow it.
+pub fn parse(input: &str) -> Result<JsonValue, ParseError> {
+    parse_with_options(input, &ParseOptions::default())
+}
+
+/// Parses a complete JSON document using the given grammar options.
+pub fn parse_with_options(input: &str, options: &ParseOptions) -> Result<JsonValue, ParseError> {
+    let mut lexer = Lexer::with_options(input, options.clone());
+    parse_document(&mut lexer, options, ParseMode::BuildValue)
+}
+
+// Lexes everything left in `lexer` and parses it as exactly one value
+fn parse_document(lexer: &mut Lexer, options: &ParseOptions, mode: ParseMode) -> Result<JsonValue, ParseError> {
+    let (tokens, positions) = lexer.lex_with_positions()?;
+    parse_tokens(tokens, positions, options, mode)
+}
+
+// Parses already lexed tokens as exactly one value
+fn parse_tokens(tokens: Vec<Token>, positions: Vec<Position>, options: &ParseOptions, mode: ParseMode) -> Result<JsonValue, ParseError> {
+    let mut parser = Parser::with_options(tokens, positions, options.clone()).with_mode(mode);
+    run_parser(&mut parser)
+}
+
+fn run_parser(parser: &mut Parser) -> Result<JsonValue, ParseError> {
+    let result = parser.parse_value().and_then(|value| match parser.is_at_end() {
+        true => Ok(value),
+        false => Err("Unexpected token after JSON value"),
+    });
+    result.map_err(|e| {
+        parser.note_error(e);
+        syntax_error(parser, e)
+    })
+}
+
+pub(crate) fn syntax_error(parser: &Parser, message: &str) -> ParseError {
+    match parser.current_position() {
+        Some(position) => ParseError::at(message, position),
+        None => ParseError::Syntax { message: message.to_string(), position: None },
+    }
+}
+
+/// Parses with `options` and narrates the work, for debugging: a line for
+/// each token lexed ("lex: read string 'key' at 1:2"), then for each
+/// container opened or closed ("parse: open object at 1:1"), and finally
+/// for where parsing stopped on an error ("parse: Expected ':', found ','
+/// at 1:6"). The trace is returned whatever the result.
+pub fn parse_with_trace(input: &str, options: &ParseOptions) -> (Result<JsonValue, ParseError>, Vec<String>) {
+    let options = ParseOptions { trace: true, ..options.clone() };
+    let mut lexer = Lexer::with_options(input, options.clone());
+    let lexed = lexer.lex_with_positions();
+    let mut trace = lexer.take_trace();
+    let (tokens, positions) = match lexed {
+        Ok(lexed) => lexed,
+        Err(e) => {
+            trace.push(format!("lex: stopped: {}", e));
+            return (Err(e), trace);
+        }
+    };
+
+    let mut parser = Parser::with_options(tokens, positions, options);
+    let result = run_parser(&mut parser);
+    trace.extend(parser.take_trace());
+    (result, trace)
+}
+
+/// Parses with `options` and also returns the comments that were found.
+///
+/// Comments are only collected when `options.allow_comments` is set;
+/// `collect_comments` is turned on automatically.
+pub fn parse_with_comments(input: &str, options: &ParseOptions) -> Result<(JsonValue, Vec<Comment>), ParseError> {
+    let options = ParseOptions { collect_comments: true, ..options.clone() };
+    let mut lexer = Lexer::with_options(input, options.clone());
+    let value = parse_document(&mut lexer, &options, ParseMode::BuildValue)?;
+    Ok((value, lexer.take_comments()))
+}
+
+#[cfg(feature = "std")]
+/// Reads everything from `reader` and parses it as a JSON document.
+///
+/// Read failures, including input that isn't valid UTF-8, are reported as
+/// `ParseError::Io`.
+pub fn from_reader<R: Read>(mut reader: R) -> Result<JsonValue, ParseError> {
+    let mut content = alloc::string::String::new();
+    reader.read_to_string(&mut content)?;
+    parse(&content)
+}
+
+/// Parses the first JSON value in `input`, ignoring anything after it.
+///
+/// Also returns the byte offset where the rest of the input starts: just
+/// past the value and any whitespace following it. This lets a buffer of
+/// concatenated values be parsed one value at a time.
+pub fn parse_prefix(input: &str) -> Result<(JsonValue, usize), ParseError> {
+    let mut stream = TokenStream::new(input);
+    let value = stream.next_element()?;
+    let end = stream.offset();
+    let rest = &input[end..];
+    Ok((value, end + rest.len() - rest.trim_start().len()))
+}
+
+/// Compares two documents token by token, ignoring whitespace and
+/// formatting, e.g. for golden-file tests. Numbers compare by value, so
+/// `1` equals `1.0`. Either side failing to lex is an error.
+pub fn tokens_equal(a: &str, b: &str) -> Result<bool, ParseError> {
+    Ok(Lexer::new(a).lex_tokens()? == Lexer::new(b).lex_tokens()?)
+}
+
+/// Parses `input` after checking it is no larger than `max_bytes`.
+///
+/// The size check happens before any lexing, so oversized payloads are
+/// rejected without paying for tokenization.
+pub fn parse_with_byte_limit(input: &str, max_bytes: usize) -> Result<JsonValue, ParseError> {
+    if input.len() > max_bytes {
+        return Err("Input exceeds byte limit".into());
+    }
+    parse(input)
+}
+
+/// Checks that `input` is a single well-formed JSON document.
+///
+/// Runs the parser in `ParseMode::ValidateOnly`, so no value is built.
+pub fn validate(input: &str) -> Result<(), ParseError> {
+    let options = ParseOptions::default();
+    parse_document(&mut Lexer::new(input), &options, ParseMode::ValidateOnly).map(|_| ())
+}
+
+/// Like `validate`, but reports how far through `input` it is by calling
+/// `progress` with the fraction read so far (see `Lexer::progress`).
+///
+/// The grammar is checked as the input is read, without collecting its
+/// tokens first, so the fraction reflects validation work done. It is
+/// reported each time at least another 1% has been checked, so at most
+/// about a hundred times, and stays below 1.0 until the document is known
+/// to be valid; 1.0 is only reported on success. Errors come in document
+/// order, so an input with both a grammar error and a later invalid
+/// character reports the grammar error, where `validate` would report the
+/// character.
+pub fn validate_with_progress<F: FnMut(f64)>(input: &str, mut progress: F) -> Result<(), ParseError> {
+    let mut stream = TokenStream::new(input);
+    let mut reported = 0.0;
+    stream.check_value(|stream| {
+        let fraction = stream.progress();
+        if fraction < 1.0 && fraction - reported >= 0.01 {
+            progress(fraction);
+            reported = fraction;
+        }
+    })?;
+    stream.finish()?;
+    progress(1.0);
+    Ok(())
+}
+
+/// Estimates how many values `input` holds, counting every scalar and
+/// container (but not object keys), without parsing it. Only the lexer
+/// runs, so this is a cheap check before deciding whether to parse
+/// untrusted input. For a valid document the count is exact; for an
+/// invalid one it is only an estimate, and lexing errors are returned.
+pub fn estimate_node_count(input: &str) -> Result<usize, ParseError> {
+    let tokens = Lexer::new(input).lex_tokens()?;
+    let mut count = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            // A string followed by ':' is a key
+            Token::String(_) if tokens.get(i + 1) == Some(&Token::Colon) => {}
+            Token::LeftBrace | Token::LeftBracket | Token::String(_) | Token::Number(_) | Token::Boolean(_) | Token::Null => count += 1,
+            _ => {}
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc_counter::allocations_during;
+
+    // Sticks to what a no_std + alloc caller has: no I/O, only alloc types.
+    // `cargo build --no-default-features` checks the library itself compiles
+    // that way.
+    #[test]
+    fn test_no_std_core_path() {
+        let input: alloc::string::String = alloc::format!("[{}, {}]", "{\"a\": 1}", "null");
+        assert!(validate(&input).is_ok());
+
+        let value = parse_with_options(&input, &ParseOptions::default()).unwrap();
+        let elements: &Vec<JsonValue> = match &value {
+            JsonValue::Array(elements) => elements,
+            _ => panic!("expected an array"),
+        };
+        assert_eq!(elements.len(), 2);
+        assert_eq!(to_string(&value), r#"[{"a":1},null]"#);
+        assert_eq!(validate("[1,").unwrap_err().message(), "Expected value");
+    }
+
+    #[test]
+    fn test_parse_any_root() {
+        assert_eq!(parse("[1, 2]").unwrap(), JsonValue::Array(vec![JsonValue::Number(1.0), JsonValue::Number(2.0)]));
+        assert_eq!(parse(" \"text\" ").unwrap(), JsonValue::String("text".to_string()));
+        assert_eq!(parse("null").unwrap(), JsonValue::Null);
+    }
+
+    #[test]
+    fn test_parse_rejects_trailing_tokens() {
+        assert_eq!(parse("{} {}").unwrap_err().message(), "Unexpected token after JSON value");
+        assert_eq!(parse("").unwrap_err().message(), "Expected value");
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let err = parse("{\n  \"a\": 1,\n  \"b\" 2\n}").unwrap_err();
+        assert_eq!(err.message(), "Expected ':'");
+        assert_eq!(err.position(), Some(Position { line: 3, column: 7, offset: 18 }));
+        assert_eq!(err.to_string(), "Expected ':' at line 3, column 7");
+
+        let err = parse("[1, 2").unwrap_err();
+        assert_eq!(err.message(), "Unexpected end of input");
+        assert_eq!(err.position().map(|p| p.column), Some(6));
+    }
+
+    #[test]
+    fn test_forbid_empty_containers() {
+        let strict = ParseOptions { forbid_empty_containers: true, ..ParseOptions::default() };
+        for input in ["{}", "[]", r#"{"a": {"b": {}}}"#] {
+            assert!(parse(input).is_ok());
+            let err = parse_with_options(input, &strict).unwrap_err();
+            assert_eq!(err.message(), "Empty container not allowed");
+        }
+        let err = parse_with_options(r#"{"a": {"b": {}}}"#, &strict).unwrap_err();
+        assert_eq!(err.position().map(|p| p.column), Some(14));
+        assert!(parse_with_options(r#"{"a": [1, {"b": 2}]}"#, &strict).is_ok());
+    }
+
+    #[test]
+    fn test_parse_with_comments() {
+        let options = ParseOptions { allow_comments: true, ..ParseOptions::default() };
+        let input = "/* config */\n{\"debug\": true // for now\n}";
+        let (value, comments) = parse_with_comments(input, &options).unwrap();
+        assert_eq!(value.get("debug"), Some(&JsonValue::Boolean(true)));
+        let texts: Vec<&str> = comments.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec![" config ", " for now"]);
+        assert_eq!(comments[1].position.line, 2);
+
+        // Without allow_comments they're still syntax errors
+        assert!(parse_with_comments(input, &ParseOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let reader = std::io::Cursor::new(br#"{"ok": [true, null]}"#.to_vec());
+        let value = from_reader(reader).unwrap();
+        assert_eq!(value.get("ok"), Some(&JsonValue::Array(vec![JsonValue::Boolean(true), JsonValue::Null])));
+    }
+
+    #[test]
+    fn test_from_reader_errors() {
+        let err = from_reader(std::io::Cursor::new(vec![b'"', 0xff, b'"'])).unwrap_err();
+        assert!(matches!(err, ParseError::Io(_)));
+
+        let err = from_reader(std::io::Cursor::new(b"[1, 2".to_vec())).unwrap_err();
+        assert_eq!(err.message(), "Unexpected end of input");
+    }
+
+    #[test]
+    fn test_byte_limit() {
+        let input = r#"{"key": "value"}"#;
+        assert_eq!(input.len(), 16);
+        assert!(parse_with_byte_limit(input, 16).is_ok());
+        assert_eq!(parse_with_byte_limit(input, 15).unwrap_err().message(), "Input exceeds byte limit");
+    }
+
+    #[test]
+    fn test_byte_limit_counts_bytes_not_chars() {
+        // "é" is two bytes in UTF-8
+        assert!(parse_with_byte_limit(r#""é""#, 4).is_ok());
+        assert!(parse_with_byte_limit(r#""é""#, 3).is_err());
+    }
+
+    #[test]
+    fn test_canonicalize_numbers() {
+        let options = ParseOptions { canonicalize_numbers: true, ..ParseOptions::default() };
+        let forms: Vec<_> = ["1", "1.0", "1e0"].iter()
+            .map(|n| to_string(&parse_with_options(n, &options).unwrap()))
+            .collect();
+        assert_eq!(forms, vec!["1", "1", "1"]);
+
+        assert_eq!(to_string(&parse_with_options("[-0, -0.0, 0e5]", &options).unwrap()), "[0,0,0]");
+        assert_eq!(to_string(&parse("-0").unwrap()), "-0");
+    }
+
+    #[test]
+    fn test_parse_prefix_concatenated_values() {
+        let buffer = r#"{"a":1} [2]"#;
+        let (first, used) = parse_prefix(buffer).unwrap();
+        assert_eq!(first, parse(r#"{"a":1}"#).unwrap());
+        assert_eq!(used, 8);
+
+        let (second, rest) = parse_prefix(&buffer[used..]).unwrap();
+        assert_eq!(second, JsonValue::Array(vec![JsonValue::Number(2.0)]));
+        assert_eq!(used + rest, buffer.len());
+
+        assert_eq!(parse_prefix("  \"x\"\n\n").unwrap(), (JsonValue::String("x".to_string()), 7));
+        assert_eq!(parse_prefix("[1, 2").unwrap_err().message(), "Unexpected end of input");
+    }
+
+    #[test]
+    fn test_tokens_equal() {
+        assert!(tokens_equal(r#"{ "a" : 1 }"#, r#"{"a":1}"#).unwrap());
+        assert!(tokens_equal("[\n  true,\n  null\n]", "[true,null]").unwrap());
+        assert!(!tokens_equal(r#"{ "a" : 1 }"#, r#"{"a":2}"#).unwrap());
+        assert!(!tokens_equal(r#"{"a":1,"b":2}"#, r#"{"b":2,"a":1}"#).unwrap());
+        assert_eq!(tokens_equal("[1]", "[1 @]").unwrap_err().message(), "Invalid character in JSON");
+    }
+
+    #[test]
+    fn test_validate_only_allocates_less() {
+        let elements: Vec<String> = (0..2000).map(|i| format!(r#"{{"id": {}, "name": "user{}", "tags": ["a", "b"]}}"#, i, i)).collect();
+        let input = format!("[{}]", elements.join(","));
+
+        let building = allocations_during(|| {
+            parse(&input).unwrap();
+        });
+        let validating = allocations_during(|| validate(&input).unwrap());
+        assert!(validating * 3 < building * 2, "validate: {}, parse: {}", validating, building);
+
+        assert_eq!(validate("[1, {\"a\": }]").unwrap_err().message(), "Expected value");
+        assert_eq!(validate("[1] 2").unwrap_err().message(), "Unexpected token after JSON value");
+    }
+
+    #[test]
+    fn test_validate_with_progress() {
+        let input = format!("[{}]", vec![r#"{"id": 12345, "tags": ["a", "b"]}"#; 5000].join(", "));
+        let mut reports = Vec::new();
+        assert!(validate_with_progress(&input, |fraction| reports.push(fraction)).is_ok());
+
+        assert_eq!(reports.last(), Some(&1.0));
+        assert!(reports.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(reports.len() > 50 && reports.len() <= 101, "{} reports", reports.len());
+
+        // 1.0 means the document was checked and found valid
+        let mut last = 0.0;
+        assert!(validate_with_progress("[1, 2", |fraction| last = fraction).is_err());
+        assert!(last < 1.0);
+        let mut last = 0.0;
+        assert!(validate_with_progress("[1, @]", |fraction| last = fraction).is_err());
+        assert!(last < 1.0);
+        let mut last = 0.0;
+        assert!(validate_with_progress("[1] [2]", |fraction| last = fraction).is_err());
+        assert!(last < 1.0);
+        let mut reports = Vec::new();
+        assert!(validate_with_progress("[]", |fraction| reports.push(fraction)).is_ok());
+        assert_eq!(reports, vec![1.0]);
+    }
+
+    #[test]
+    fn test_validate_with_progress_errors_match_validate() {
+        let inputs = [
+            "", "[", "{", "[1", "[1,", "[1, ]", "[1 2]", "[,1]", "[}", "{]", "{,}", r#"{"a"}"#, r#"{"a""#,
+            r#"{"a":"#, r#"{"a":1,"#, r#"{"a":1,}"#, r#"{"a" 1}"#, r#"{1: 2}"#, r#"{"a": 1 "b": 2}"#,
+            "]", "[1] 2", r#"{"a": [1, {"b": [true, null,]}]}"#, "  [\n  1,\n  2\n  ", "@", "[1, @]",
+        ];
+        for input in inputs {
+            assert_eq!(validate_with_progress(input, |_| {}), validate(input), "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_with_trace() {
+        let (result, trace) = parse_with_trace(r#"{"a": [1, true]}"#, &ParseOptions::default());
+        assert_eq!(result.unwrap(), parse(r#"{"a": [1, true]}"#).unwrap());
+        assert_eq!(trace, vec![
+            "lex: read '{' at 1:1",
+            "lex: read string 'a' at 1:2",
+            "lex: read ':' at 1:5",
+            "lex: read '[' at 1:7",
+            "lex: read number 1 at 1:8",
+            "lex: read ',' at 1:9",
+            "lex: read true at 1:11",
+            "lex: read ']' at 1:15",
+            "lex: read '}' at 1:16",
+            "parse: open object at 1:1",
+            "parse: open array at 1:7",
+            "parse: close array at 1:15",
+            "parse: close object at 1:16",
+        ]);
+
+        let (result, trace) = parse_with_trace(r#"{"a", 1}"#, &ParseOptions::default());
+        assert_eq!(result.unwrap_err().message(), "Expected ':'");
+        assert_eq!(trace.last().unwrap(), "parse: Expected ':', found ',' at 1:5");
+
+        let (_, trace) = parse_with_trace("[1, @]", &ParseOptions::default());
+        assert_eq!(trace.last().unwrap(), "lex: stopped: Invalid character in JSON at line 1, column 5");
+        // Tracing is off unless asked for
+        let mut lexer = Lexer::new("[1]");
+        lexer.lex_tokens().unwrap();
+        assert!(lexer.take_trace().is_empty());
+    }
+
+    #[test]
+    fn test_estimate_node_count() {
+        let input = r#"{"name": "ana", "tags": ["a", "b", []], "owner": {"id": 7, "active": true, "manager": null}, "scores": [[1, 2], {}]}"#;
+        assert_eq!(estimate_node_count(input).unwrap(), parse_arena(input).unwrap().node_count());
+        assert_eq!(estimate_node_count(input).unwrap(), 15);
+        assert_eq!(estimate_node_count("42").unwrap(), 1);
+        assert_eq!(estimate_node_count(r#"{"": ""}"#).unwrap(), 2);
+        assert!(estimate_node_count("[1, @]").is_err());
+    }
+}
@@ -1,337 +1,488 @@
 use std::env;
 use std::fs;
+use std::io::{self, Read, Write};
 use std::process;
+use std::time::{Duration, Instant};
 
-// Token Definition
-#[derive(Debug, PartialEq)]
-enum Token {
-    LeftBrace,       // Represents {
-    RightBrace,      // Represents }
-    LeftBracket,     // Represents [
-    RightBracket,    // Represents ]
-    String(String),  // Represents any string value (both keys and values)
-    Number(f64),     // Represents any number value
-    Boolean(bool),   // Represents any boolean value
-    Null,
-    Colon,           // Represents :
-    Comma,           // Represents ,
-}
-
-#[derive(Debug)]
-struct Lexer {
-    input: Vec<char>,
-    position: usize,
-}
+use json_parser::{find_unsorted_keys, parse, parse_collecting, parse_with_options, parse_with_trace, pointer, repair, sort_keys, to_string, to_string_pretty, JsonValue, Lexer, ParseError, ParseOptions, Token};
 
-impl Lexer {
-    fn new(input: &str) -> Self {
-        Lexer {
-            input: input.chars().collect(),
-            position: 0,
-        }
-    }
+// Environment variable consulted when no filename is given
+const INPUT_VAR: &str = "JSON_INPUT";
 
-    fn peek(&self) -> Option<char> {
-        self.input.get(self.position).copied()
-    }
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
 
-    fn advance(&mut self) {
-        self.position += 1
-    }
+#[derive(Debug, PartialEq)]
+enum Input {
+    File(String),
+    Stdin,
+}
 
-    fn read_while<F>(&mut self, predicate: F) -> String
-    where F: Fn(char) -> bool {
-        let mut result = String::new();
-        while let Some(c) = self.peek() {
-            if predicate(c) {
-                result.push(c);
-                self.advance();
-            } else {
-                break;
-            }
+impl Input {
+    // Name used when reporting errors
+    fn label(&self) -> &str {
+        match self {
+            Input::File(path) => path,
+            Input::Stdin => "<stdin>",
         }
-        result
     }
+}
 
-    fn lex_string(&mut self) -> Result<String, &'static str> {
-        let mut result = String::new();
-        self.advance(); // Skip opening quote
+struct Config {
+    inputs: Vec<Input>,
+    one_line_errors: bool,
+    github_annotations: bool,
+    quiet: bool,
+    errors_to_stderr: bool,
+    fail_fast: bool,
+    pretty_sorted: bool,
+    pretty: bool,
+    extract: Option<String>,
+    strip_comments: bool,
+    check_sorted: bool,
+    tree: bool,
+    max_errors: Option<usize>,
+    explain: bool,
+    repair: bool,
+    token_profile: bool,
+    infer_schema: bool,
+    bom_out: bool,
+    time: bool,
+}
 
-        while let Some(c) = self.peek() {
-            match c {
-                '"' => {
-                    self.advance();
-                    return Ok(result);
-                }
-                '\\' => return Err("Escape sequences not yet supported"),
-                '\n' => return Err("Unterminated string literal"),
-                c => {
-                    result.push(c);
-                    self.advance();
-                }
+// Resolves the input as: explicit filename arguments, then the JSON_INPUT
+// environment variable (looked up through `env_lookup`), then stdin.
+// Several files are validated one after another; printing a document
+// (--pretty, --extract, ...), --time, --max-errors and --explain need a
+// single input.
+fn parse_args<F>(args: &[String], env_lookup: F) -> Result<Config, String>
+where F: Fn(&str) -> Option<String> {
+    let usage = format!("Usage: {} [--one-line-errors] [--github-annotations] [--quiet] [--errors-to-stderr] [--fail-fast] [--pretty-sorted] [--pretty] [--extract=POINTER] [--strip-comments] [--check-sorted] [--tree] [--max-errors=N] [--explain] [--repair] [--token-profile] [--infer-schema] [--bom-out] [--time] [filename...]", args[0]);
+    let mut config = Config {
+        inputs: Vec::new(),
+        one_line_errors: false,
+        github_annotations: false,
+        quiet: false,
+        errors_to_stderr: false,
+        fail_fast: false,
+        pretty_sorted: false,
+        pretty: false,
+        extract: None,
+        strip_comments: false,
+        check_sorted: false,
+        tree: false,
+        max_errors: None,
+        explain: false,
+        repair: false,
+        token_profile: false,
+        infer_schema: false,
+        bom_out: false,
+        time: false,
+    };
+    for arg in &args[1..] {
+        match arg.as_str() {
+            "--one-line-errors" => config.one_line_errors = true,
+            "--github-annotations" => config.github_annotations = true,
+            "--quiet" => config.quiet = true,
+            "--errors-to-stderr" => config.errors_to_stderr = true,
+            "--fail-fast" => config.fail_fast = true,
+            "--pretty-sorted" => config.pretty_sorted = true,
+            "--pretty" => config.pretty = true,
+            "--strip-comments" => config.strip_comments = true,
+            "--check-sorted" => config.check_sorted = true,
+            "--tree" => config.tree = true,
+            "--explain" => config.explain = true,
+            "--repair" => config.repair = true,
+            "--token-profile" => config.token_profile = true,
+            "--infer-schema" => config.infer_schema = true,
+            "--bom-out" => config.bom_out = true,
+            "--time" => config.time = true,
+            _ if arg.starts_with("--max-errors=") => {
+                config.max_errors = Some(arg["--max-errors=".len()..].parse().map_err(|_| usage.clone())?);
             }
+            _ if arg.starts_with("--extract=") => config.extract = Some(arg["--extract=".len()..].to_string()),
+            _ if arg.starts_with("--") => return Err(usage),
+            _ => config.inputs.push(Input::File(arg.clone())),
         }
-        Err("Unterminated string literal")
     }
 
-    fn lex_number(&mut self) -> Result<f64, &'static str> {
-        let number_str = self.read_while(|c| {
-            c.is_ascii_digit() || c == '-' || c == '.' || c == 'e' || c == 'E' || c == '+'
+    if config.inputs.is_empty() {
+        config.inputs.push(match env_lookup(INPUT_VAR).filter(|path| !path.is_empty()) {
+            Some(path) => Input::File(path),
+            None => Input::Stdin,
         });
-
-        number_str.parse::<f64>()
-        .map_err(|_| "Invalid number format")
     }
+    let prints_document = config.pretty || config.pretty_sorted || config.tree || config.extract.is_some() || config.strip_comments || config.repair || config.token_profile || config.infer_schema;
+    let single_input_only = config.time || config.max_errors.is_some() || config.explain;
+    if config.inputs.len() > 1 && (prints_document || single_input_only) {
+        return Err(usage);
+    }
+    // A BOM only makes sense in front of a printed document
+    if config.bom_out && !prints_document {
+        return Err(usage);
+    }
+    Ok(config)
+}
 
-    fn lex_identifier(&mut self) -> Result<Token, &'static str> {
-        let identifier = self.read_while(|c| c.is_ascii_alphabetic());
-        
-        match identifier.as_str() {
-            "true" => Ok(Token::Boolean(true)),
-            "false" => Ok(Token::Boolean(false)),
-            "null" => Ok(Token::Null),
-            _ => Err("Invalid identifier")
+fn read_input(input: &Input) -> io::Result<String> {
+    match input {
+        Input::File(path) => fs::read_to_string(path),
+        Input::Stdin => {
+            let mut buffer = String::new();
+            io::stdin().read_to_string(&mut buffer)?;
+            Ok(buffer)
         }
     }
+}
 
-    // Main lexing function that produces tokens
-    fn lex_tokens(&mut self) -> Result<Vec<Token>, &'static str> {
-        let mut tokens = Vec::new();
+// Formats an error as a single greppable line: ERROR file.json:12:5: message
+fn format_one_line_error(filename: &str, err: &ParseError) -> String {
+    match err.position() {
+        Some(position) => format!("ERROR {}:{}:{}: {}", filename, position.line, position.column, err.message()),
+        None => format!("ERROR {}: {}", filename, err.message()),
+    }
+}
 
-        while let Some(c) = self.peek() {
-            match c {
-                '{' => {
-                    tokens.push(Token::LeftBrace);
-                    self.advance();
-                },
-                '}' => {
-                    tokens.push(Token::RightBrace);
-                    self.advance();
-                },
-                '[' => {
-                    tokens.push(Token::LeftBracket);
-                    self.advance();
-                },
-                ']' => {
-                    tokens.push(Token::RightBracket);
-                    self.advance();
-                },
-                ':' => {
-                    tokens.push(Token::Colon);
-                    self.advance();
-                },
-                ',' => {
-                    tokens.push(Token::Comma);
-                    self.advance();
-                },
-                '"' => {
-                    let string = self.lex_string()?;
-                    tokens.push(Token::String(string));
-                },
-                c if c.is_ascii_digit() || c == '-' => {
-                    let number = self.lex_number()?;
-                    tokens.push(Token::Number(number));
-                },
-                c if c.is_ascii_alphabetic() => {
-                    let token = self.lex_identifier()?;
-                    tokens.push(token);
-                }
-                c if c.is_whitespace() => {
-                    self.advance();
-                },
-                _ => return Err("Invalid character in JSON"),
-            }
-        }
-        Ok(tokens)
+// Formats an error as a GitHub Actions workflow command, so it shows up
+// inline on the file: ::error file=a.json,line=3,col=5::message
+fn format_github_annotation(filename: &str, err: &ParseError) -> String {
+    // Workflow commands reserve these characters in values
+    let escape = |text: &str, property: bool| {
+        let text = text.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A");
+        if property { text.replace(':', "%3A").replace(',', "%2C") } else { text }
+    };
+    let file = escape(filename, true);
+    match err.position() {
+        Some(position) => format!("::error file={},line={},col={}::{}", file, position.line, position.column, escape(err.message(), false)),
+        None => format!("::error file={}::{}", file, escape(err.message(), false)),
     }
 }
 
-struct Parser {
-    tokens: Vec<Token>,
-    position: usize,
+// Indented output with object keys sorted at every level
+fn format_pretty_sorted(value: &JsonValue) -> String {
+    to_string_pretty(&sort_keys(value))
 }
 
-impl Parser {
-    fn new(tokens: Vec<Token>) -> Self {
-        Parser {
-            tokens,
-            position: 0,
+// Parses a single input; --strip-comments accepts JSONC comments. With
+// --max-errors, parsing carries on past errors and reports up to that many.
+// --explain writes a trace of the lexer and parser to `err` first.
+fn parse_document(config: &Config, content: &str, err: &mut dyn Write) -> io::Result<Result<JsonValue, Vec<ParseError>>> {
+    let options = ParseOptions { allow_comments: config.strip_comments, ..ParseOptions::default() };
+    if config.explain {
+        let (result, trace) = parse_with_trace(content, &options);
+        for line in trace {
+            writeln!(err, "{}", line)?;
         }
+        return Ok(result.map_err(|e| vec![e]));
     }
+    Ok(match config.max_errors {
+        Some(max_errors) => parse_collecting(content, &options, max_errors),
+        None => parse_with_options(content, &options).map_err(|e| vec![e]),
+    })
+}
 
-    fn peek(&self) -> Option<&Token> {
-        self.tokens.get(self.position)
+// How many tokens of each kind the lexer produced, one "kind: count" line
+// each, for --token-profile. Braces and brackets count both the opening
+// and closing ones.
+fn format_token_profile(tokens: &[Token]) -> String {
+    let mut counts = [("braces", 0), ("brackets", 0), ("strings", 0), ("numbers", 0), ("booleans", 0), ("nulls", 0), ("commas", 0), ("colons", 0)];
+    for token in tokens {
+        let kind = match token {
+            Token::LeftBrace | Token::RightBrace => 0,
+            Token::LeftBracket | Token::RightBracket => 1,
+            Token::String(_) => 2,
+            Token::Number(_) => 3,
+            Token::Boolean(_) => 4,
+            Token::Null => 5,
+            Token::Comma => 6,
+            Token::Colon => 7,
+        };
+        counts[kind].1 += 1;
     }
+    counts.iter().map(|(name, count)| format!("{}: {}", name, count)).collect::<Vec<_>>().join("\n")
+}
 
-    fn advance(&mut self) {
-        self.position += 1
-    }
+// An ASCII outline of the value for --tree. The root line names its type;
+// below it every member or element gets a branch, labelled with its key or
+// [index], and scalars and empty containers are printed inline:
+//
+// object
+// ├─ name: "ana"
+// └─ tags:
+//    └─ [0]: "a"
+fn format_tree(value: &JsonValue) -> String {
+    let mut lines = vec![match value {
+        JsonValue::Array(elements) if !elements.is_empty() => "array".to_string(),
+        JsonValue::Object(entries) if !entries.is_empty() => "object".to_string(),
+        other => to_string(other),
+    }];
+    tree_children(value, "", &mut lines);
+    lines.join("\n")
+}
 
-    fn parse_value(&mut self) -> Result<(), &'static str> {
-        match self.peek() {
-            Some(Token::LeftBrace) => self.parse_object(),
-            Some(Token::LeftBracket) => self.parse_array(),
-            Some(Token::String(_)) |
-            Some(Token::Number(_)) |
-            Some(Token::Boolean(_)) |
-            Some(Token::Null) => {
-                self.advance();
-                Ok(())
-            }
-            _ => Err("Expected value"),
+fn tree_children(value: &JsonValue, prefix: &str, lines: &mut Vec<String>) {
+    let children: Vec<(String, &JsonValue)> = match value {
+        JsonValue::Array(elements) => elements.iter().enumerate().map(|(i, v)| (format!("[{}]", i), v)).collect(),
+        JsonValue::Object(entries) => entries.iter().map(|(k, v)| (k.clone(), v)).collect(),
+        _ => return,
+    };
+    for (i, (label, child)) in children.iter().enumerate() {
+        let last = i + 1 == children.len();
+        let branch = if last { "└─ " } else { "├─ " };
+        match child {
+            JsonValue::Array(items) if !items.is_empty() => lines.push(format!("{}{}{}:", prefix, branch, label)),
+            JsonValue::Object(items) if !items.is_empty() => lines.push(format!("{}{}{}:", prefix, branch, label)),
+            scalar => lines.push(format!("{}{}{}: {}", prefix, branch, label, to_string(scalar))),
         }
+        let indent = if last { "   " } else { "│  " };
+        tree_children(child, &format!("{}{}", prefix, indent), lines);
     }
+}
 
-    fn parse_array(&mut self) -> Result<(), &'static str> {
-        // Consume the opening bracket
-        match self.peek() {
-            Some(Token::LeftBracket) => self.advance(),
-            _ => return Err("Expected '['"),
-        }
-
-        let mut first = true;
-        while let Some(token) = self.peek() {
-            match token {
-                // Case 1: We see a closing bracket and we're at the first position
-                Token::RightBracket if first => {
-                    self.advance();
-                    return Ok(())  // Empty array [] is valid
-                }
+// A draft-07 JSON Schema describing `value`, for --infer-schema. Every
+// key of an object is listed under "properties" and "required". Array
+// items get one schema when every element has the same one, and "anyOf"
+// the distinct ones otherwise. Numbers are all "number".
+fn infer_schema(value: &JsonValue) -> JsonValue {
+    let mut schema = vec![("$schema".to_string(), JsonValue::String("http://json-schema.org/draft-07/schema#".to_string()))];
+    if let JsonValue::Object(entries) = schema_of(value) {
+        schema.extend(entries);
+    }
+    JsonValue::Object(schema)
+}
 
-                // Case 2: We see a closing bracket after some values
-                Token::RightBracket => {
-                    self.advance();
-                    return Ok(());  // Array is properly closed
+fn schema_of(value: &JsonValue) -> JsonValue {
+    let type_name = match value {
+        JsonValue::Null => "null",
+        JsonValue::Boolean(_) => "boolean",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    };
+    let mut schema = vec![("type".to_string(), JsonValue::String(type_name.to_string()))];
+    match value {
+        JsonValue::Array(elements) if !elements.is_empty() => {
+            let mut distinct: Vec<JsonValue> = Vec::new();
+            for element in elements {
+                let element = schema_of(element);
+                if !distinct.contains(&element) {
+                    distinct.push(element);
                 }
-
-                // Case 3: We see a comma after a value (not first)
-                Token::Comma if !first => {
-                    self.advance();
-                    // After a comma, check for trailing comma
-                    match self.peek() {
-                        Some(Token::RightBracket) => {
-                            // {"key": "value",} is invalid
-                            return Err("Trailing comma not allowed")
-                        }
-                        _ => {}  // Otherwise comma is okay
-                    }
+            }
+            let items = if distinct.len() == 1 {
+                distinct.remove(0)
+            } else {
+                JsonValue::Object(vec![("anyOf".to_string(), JsonValue::Array(distinct))])
+            };
+            schema.push(("items".to_string(), items));
+        }
+        JsonValue::Object(entries) if !entries.is_empty() => {
+            let mut properties: Vec<(String, JsonValue)> = Vec::new();
+            for (key, child) in entries {
+                // A repeated key is described by its first value
+                if !properties.iter().any(|(k, _)| k == key) {
+                    properties.push((key.clone(), schema_of(child)));
                 }
-                
-                // Case 4: We're not at first item and don't see comma or rightbracket
-                _ if !first => return Err("Expected ',' or ']'"),
-                
-                // Case 5: Any other token, continue processing
-                _ => {}
             }
-            self.parse_value()?;
-            first = false;
+            let required = properties.iter().map(|(k, _)| JsonValue::String(k.clone())).collect();
+            schema.push(("properties".to_string(), JsonValue::Object(properties)));
+            schema.push(("required".to_string(), JsonValue::Array(required)));
         }
-        Err("Unexpected end of input")
+        _ => {}
     }
+    JsonValue::Object(schema)
+}
 
-    fn parse_object(&mut self) -> Result<(), &'static str> {
-        //Expect opening brace
-        match self.peek() {
-            Some(Token::LeftBrace) => self.advance(),
-            _ => return Err("Expected '{'"),
+// For --check-sorted: names the first object whose keys are out of order
+fn unsorted_keys_message(value: &JsonValue) -> Option<String> {
+    find_unsorted_keys(value).map(|(ptr, key)| {
+        let object = if ptr.is_empty() { "the root object".to_string() } else { format!("the object at {}", ptr) };
+        format!("Keys not sorted: `{}` is out of order in {}", key, object)
+    })
+}
+
+// What to print for a parsed document: the subtree picked by --extract
+// (compact, or indented with --pretty), the whole document when only a
+// formatting flag or --strip-comments is given, or just "Valid JSON"
+fn render(config: &Config, value: &JsonValue) -> Result<String, String> {
+    let value = match &config.extract {
+        Some(ptr) => pointer(value, ptr).ok_or_else(|| format!("Pointer {} not found", ptr))?,
+        None => value,
+    };
+
+    Ok(if config.infer_schema {
+        to_string_pretty(&infer_schema(value))
+    } else if config.tree {
+        format_tree(value)
+    } else if config.pretty_sorted {
+        format_pretty_sorted(value)
+    } else if config.pretty {
+        to_string_pretty(value)
+    } else if config.extract.is_some() || config.strip_comments {
+        to_string(value)
+    } else {
+        "Valid JSON".to_string()
+    })
+}
+
+// Validates every input in turn, writing one line per file, and returns
+// how many were invalid (unreadable files count as invalid). Without
+// --fail-fast all files are checked and a summary like "3 of 10 files
+// invalid" follows; with it, checking stops at the first failure.
+// --errors-to-stderr writes the lines for invalid files to `err`.
+fn validate_files(config: &Config, out: &mut dyn Write, err: &mut dyn Write) -> io::Result<usize> {
+    let mut invalid = 0;
+    for input in &config.inputs {
+        let label = input.label();
+        let (valid, line) = match read_input(input) {
+            Ok(content) => match parse(&content) {
+                Ok(value) => match config.check_sorted.then(|| unsorted_keys_message(&value)).flatten() {
+                    Some(message) => (false, format!("{}: {}", label, message)),
+                    None => (true, format!("{}: Valid JSON", label)),
+                },
+                Err(e) if config.github_annotations => (false, format_github_annotation(label, &e)),
+                Err(e) if config.one_line_errors => (false, format_one_line_error(label, &e)),
+                Err(e) => (false, format!("{}: Invalid JSON: {}", label, e)),
+            },
+            Err(e) => (false, format!("{}: Error reading file: {}", label, e)),
+        };
+        let report: &mut dyn Write = if !valid && config.errors_to_stderr { &mut *err } else { &mut *out };
+        writeln!(report, "{}", line)?;
+
+        if !valid {
+            invalid += 1;
+            if config.fail_fast {
+                return Ok(invalid);
+            }
         }
+    }
+    writeln!(out, "{} of {} files invalid", invalid, config.inputs.len())?;
+    Ok(invalid)
+}
 
-        let mut first = true;
-        while let Some(token) = self.peek() {
-            match token {
-                // Case 1: We see a closing brace and we're at the first position
-                Token::RightBrace if first => {
-                    self.advance();
-                    return Ok(()); // Empty object {} is valid
-                }
+// The --time report: how long parsing `bytes` took and the throughput in
+// MB/s (10^6 bytes). Small files parse in microseconds, so their
+// throughput is dominated by timer resolution and fixed costs; only
+// figures for larger inputs are worth comparing.
+fn format_timing(bytes: usize, elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs_f64();
+    let throughput = if seconds > 0.0 { format!("{:.1}", bytes as f64 / 1e6 / seconds) } else { "-".to_string() };
+    format!("Parsed {} bytes in {:.3} ms ({} MB/s)", bytes, seconds * 1e3, throughput)
+}
 
-                // Case 2: We see a closing brace after some key-value pairs
-                Token::RightBrace => {
-                    self.advance();
-                    return Ok(());  // Object is properly closed
-                }
+// Writes a printed document and its newline, after a UTF-8 byte order
+// mark with --bom-out
+fn write_document(config: &Config, out: &mut dyn Write, document: &str) -> io::Result<()> {
+    if config.bom_out {
+        out.write_all(UTF8_BOM)?;
+    }
+    writeln!(out, "{}", document)
+}
 
-                // Case 3: We see a comma after a key-value pair (not first)
-                Token::Comma if !first => {
-                    self.advance();
-                    // After a comma, check for trailing comma
-                    match self.peek() {
-                        Some(Token::RightBrace) => {
-                            // {"key": "value",} is invalid
-                            return Err("Trailing comma not allowed")
-                        }
-                        _ => {}  // Otherwise comma is okay
-                    }
-                }
+// Validates (and possibly prints) the inputs, writing results to `out`
+// and other problems to `err`. Returns whether every input was valid.
+// With --quiet nothing reaches `out`; the exit code says it all.
+// --errors-to-stderr sends the invalid-JSON reports to `err` instead, so
+// they still show up in quiet mode.
+fn run(config: &Config, out: &mut dyn Write, err: &mut dyn Write) -> io::Result<bool> {
+    let mut sink = io::sink();
+    let out: &mut dyn Write = if config.quiet { &mut sink } else { out };
+
+    if config.inputs.len() > 1 {
+        return Ok(validate_files(config, out, err)? == 0);
+    }
 
-                // Case 4: We're not at first item and don't see comma or rightbrace
-                _ if !first => {
-                    // If we've already processed a pair but don't see 
-                    // a comma or closing brace, it's an error
-                    return Err("Expected ',' or '}'")
-                }
+    let input = &config.inputs[0];
+    let content = match read_input(input) {
+        Ok(content) => content,
+        Err(e) => {
+            writeln!(err, "Error reading file: {}", e)?;
+            return Ok(false);
+        }
+    };
 
-                // Case 5: Any other token, continue processing
-                _ => {}
+    // --repair prints the fixed-up document instead of validating it
+    if config.repair {
+        return match repair(&content) {
+            Ok(repaired) => {
+                write_document(config, out, &repaired)?;
+                Ok(true)
             }
-
-            // Parse key
-            match self.peek() {
-                Some(Token::String(_)) => self.advance(),
-                _ => return Err("Expected string key"),
+            Err(e) => {
+                writeln!(err, "Could not repair: {}", e)?;
+                Ok(false)
             }
+        };
+    }
 
-            // Parse colon
-            match self.peek() {
-                Some(Token::Colon) => self.advance(),
-                _ => return Err("Expected ':'"),
+    // --token-profile only lexes, so the tokens needn't form a document
+    if config.token_profile {
+        let options = ParseOptions { allow_comments: config.strip_comments, ..ParseOptions::default() };
+        return match Lexer::with_options(&content, options).lex_tokens() {
+            Ok(tokens) => {
+                writeln!(out, "{}", format_token_profile(&tokens))?;
+                Ok(true)
+            }
+            Err(e) => {
+                let report: &mut dyn Write = if config.errors_to_stderr { err } else { out };
+                writeln!(report, "Invalid JSON: {}", e)?;
+                Ok(false)
             }
+        };
+    }
 
-            // Parse value (now recursive)
-            self.parse_value()?;
+    let started = Instant::now();
+    let parsed = parse_document(config, &content, err)?;
+    if config.time {
+        writeln!(err, "{}", format_timing(content.len(), started.elapsed()))?;
+    }
 
-            first = false;
+    match parsed {
+        Ok(value) => match config.check_sorted.then(|| unsorted_keys_message(&value)).flatten() {
+            Some(message) => {
+                writeln!(out, "{}", message)?;
+                Ok(false)
+            }
+            None => match render(config, &value) {
+                Ok(output) => {
+                    write_document(config, out, &output)?;
+                    Ok(true)
+                }
+                Err(e) => {
+                    writeln!(err, "Error: {}", e)?;
+                    Ok(false)
+                }
+            },
+        },
+        Err(errors) => {
+            let report: &mut dyn Write = if config.errors_to_stderr { err } else { out };
+            for e in errors {
+                if config.github_annotations {
+                    writeln!(report, "{}", format_github_annotation(input.label(), &e))?;
+                } else if config.one_line_errors {
+                    writeln!(report, "{}", format_one_line_error(input.label(), &e))?;
+                } else {
+                    writeln!(report, "Invalid JSON: {}", e)?;
+                }
+            }
+            Ok(false)
         }
-
-        Err("Unexpected end of input")
     }
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        eprintln!("Usage: {} <filename>", args[0]);
-        process::exit(1);
-    }
-
-    let filename = &args[1];
-    let content = match fs::read_to_string(filename) {
-        Ok(content) => content,
-        Err(e) => {
-            eprintln!("Error reading file: {}", e);
-            process::exit(1);
-        }
-    };
-
-    let mut lexer = Lexer::new(&content);
-    let tokens = match lexer.lex_tokens() {
-        Ok(tokens) => tokens,
-        Err(e) => {
-            println!("Invalid JSON: {}", e);
+    let config = match parse_args(&args, |key| env::var(key).ok()) {
+        Ok(config) => config,
+        Err(usage) => {
+            eprintln!("{}", usage);
             process::exit(1);
         }
     };
 
-    let mut parser = Parser::new(tokens);
-    match parser.parse_object() {
-        Ok(_) => {
-            println!("Valid JSON");
-            process::exit(0);
-        }
-        Err(e) => {
-            println!("Invalid JSON: {}", e);
-            process::exit(1);
-        }
+    match run(&config, &mut io::stdout().lock(), &mut io::stderr().lock()) {
+        Ok(true) => process::exit(0),
+        _ => process::exit(1),
     }
 }
 
@@ -339,188 +490,378 @@ fn main() {
 mod tests {
     use super::*;
 
-    fn parse_json(input: &str) -> Result<(), &'static str> {
-        let mut lexer = Lexer::new(input);
-        let tokens = lexer.lex_tokens()?;
-        let mut parser = Parser::new(tokens);
-        parser.parse_object()
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
     }
 
-    // Tests for Valid JSON
     #[test]
-    fn test_empty_object() {
-        assert!(parse_json("{}").is_ok());
+    fn test_one_line_error_format() {
+        let err = parse("{\n  \"a\": [1,\n    12.34.56]\n}").unwrap_err();
+        assert_eq!(format_one_line_error("file.json", &err), "ERROR file.json:3:5: Invalid number format");
     }
 
     #[test]
-    fn test_basic_types() {
-        // Test all basic JSON types
-        assert!(parse_json(r#"{
-            "string": "hello_world",
-            "number": 42,
-            "float": 3.14,
-            "negative": -123,
-            "boolean_true": true,
-            "boolean_false": false,
-            "null_value": null
-        }"#).is_ok());
+    fn test_github_annotation_format() {
+        let err = parse("{\n  \"a\": [1,\n    12.34.56]\n}").unwrap_err();
+        assert_eq!(format_github_annotation("data/file.json", &err), "::error file=data/file.json,line=3,col=5::Invalid number format");
+        assert_eq!(format_github_annotation("a,b.json", &ParseError::Io("100% broken".to_string())), "::error file=a%2Cb.json::100%25 broken");
     }
 
     #[test]
-    fn test_nested_structures() {
-        assert!(parse_json(r#"{
-            "empty_object": {},
-            "empty_array": [],
-            "nested_object": {"key": "value"},
-            "nested_array": ["item"],
-            "deep_nesting": {
-                "level1": {
-                    "level2": {
-                        "level3": {}
-                    }
-                }
-            }
-        }"#).is_ok());
+    fn test_pretty_sorted_output() {
+        let value = parse(r#"{"zeta": [3, 1], "alpha": {"y": true, "x": null}, "mid": 1}"#).unwrap();
+        let expected = "\
+{
+  \"alpha\": {
+    \"x\": null,
+    \"y\": true
+  },
+  \"mid\": 1,
+  \"zeta\": [
+    3,
+    1
+  ]
+}";
+        assert_eq!(format_pretty_sorted(&value), expected);
     }
 
     #[test]
-    fn test_array_variations() {
-        assert!(parse_json(r#"{
-            "mixed_array": [1, "string", true, null, {"key": "value"}, [1, 2, 3]],
-            "number_array": [1, 2, 3, 4, 5],
-            "nested_arrays": [[], [1], [1, [2, [3]]]],
-            "object_array": [{"k1": "v1"}, {"k2": "v2"}]
-        }"#).is_ok());
+    fn test_parse_args() {
+        let no_env = |_: &str| None;
+        let config = parse_args(&args(&["json_parser", "--one-line-errors", "file.json"]), no_env).unwrap();
+        assert!(config.one_line_errors);
+        assert_eq!(config.inputs, vec![Input::File("file.json".to_string())]);
+
+        assert!(!parse_args(&args(&["json_parser", "file.json"]), no_env).unwrap().one_line_errors);
+        assert!(parse_args(&args(&["json_parser", "--pretty-sorted", "file.json"]), no_env).unwrap().pretty_sorted);
+        assert!(parse_args(&args(&["json_parser", "--bogus", "file.json"]), no_env).is_err());
+        assert_eq!(parse_args(&args(&["json_parser", "a.json", "b.json"]), no_env).unwrap().inputs.len(), 2);
+        assert!(parse_args(&args(&["json_parser", "--pretty", "a.json", "b.json"]), no_env).is_err());
     }
 
     #[test]
-    fn test_whitespace_handling() {
-        // Test various whitespace scenarios
-        assert!(parse_json(r#"{"key" : "value"}"#).is_ok());
-        assert!(parse_json("{\n\t\"key\":\"value\"\n}").is_ok());
-        assert!(parse_json("{ \r\n \t }").is_ok());
+    fn test_input_resolution_order() {
+        let env = |key: &str| (key == "JSON_INPUT").then(|| "from_env.json".to_string());
+        let no_env = |_: &str| None;
+        let empty_env = |_: &str| Some(String::new());
+
+        // An explicit argument wins over the environment
+        let config = parse_args(&args(&["json_parser", "arg.json"]), env).unwrap();
+        assert_eq!(config.inputs, vec![Input::File("arg.json".to_string())]);
+
+        // Then JSON_INPUT
+        let config = parse_args(&args(&["json_parser", "--pretty-sorted"]), env).unwrap();
+        assert_eq!(config.inputs, vec![Input::File("from_env.json".to_string())]);
+
+        // Then stdin
+        assert_eq!(parse_args(&args(&["json_parser"]), no_env).unwrap().inputs, vec![Input::Stdin]);
+        assert_eq!(parse_args(&args(&["json_parser"]), empty_env).unwrap().inputs, vec![Input::Stdin]);
     }
 
-    // Tests for Invalid JSON
     #[test]
-    fn test_invalid_syntax() {
-        // Missing closing brace
-        let err = parse_json(r#"{"key": "value""#).unwrap_err();
-        assert_eq!(err, "Unexpected end of input");
+    fn test_extract_subtree() {
+        let value = parse(r#"{"users": [{"name": "ana", "tags": ["a", "b"]}, {"name": "bo"}]}"#).unwrap();
+        let no_env = |_: &str| None;
+
+        let config = parse_args(&args(&["json_parser", "--extract=/users/0", "f.json"]), no_env).unwrap();
+        assert_eq!(render(&config, &value).unwrap(), r#"{"name":"ana","tags":["a","b"]}"#);
+
+        let config = parse_args(&args(&["json_parser", "--extract=/users/0/tags", "--pretty", "f.json"]), no_env).unwrap();
+        assert_eq!(render(&config, &value).unwrap(), "[\n  \"a\",\n  \"b\"\n]");
 
-        // Missing quotes around key
-        let err = parse_json(r#"{key: "value"}"#).unwrap_err();
-        assert_eq!(err, "Invalid identifier");
+        let config = parse_args(&args(&["json_parser", "--extract=/users/5", "f.json"]), no_env).unwrap();
+        assert_eq!(render(&config, &value).unwrap_err(), "Pointer /users/5 not found");
 
-        // Missing colon
-        let err = parse_json(r#"{"key" "value"}"#).unwrap_err();
-        assert_eq!(err, "Expected ':'");
+        let config = parse_args(&args(&["json_parser", "f.json"]), no_env).unwrap();
+        assert_eq!(render(&config, &value).unwrap(), "Valid JSON");
     }
 
     #[test]
-    fn test_invalid_arrays() {
-        // Trailing comma in array
-        let err = parse_json(r#"{"arr": [1, 2, ]}"#).unwrap_err();
-        assert_eq!(err, "Trailing comma not allowed");
+    fn test_validate_files_report_all_and_fail_fast() {
+        let files = [
+            ("test_multi_1.json", "{}"),
+            ("test_multi_2.json", "[1,"),
+            ("test_multi_3.json", "[1, 2]"),
+            ("test_multi_4.json", "{\"a\" 1}"),
+        ];
+        for (name, content) in files {
+            fs::write(name, content).unwrap();
+        }
+        let mut list = vec!["json_parser"];
+        list.extend(files.iter().map(|(name, _)| *name));
+        list.push("test_multi_missing.json");
+        let mut report_all = Vec::new();
+        let invalid = validate_files(&parse_args(&args(&list), |_| None).unwrap(), &mut report_all, &mut io::sink()).unwrap();
+
+        list.insert(1, "--fail-fast");
+        let mut fail_fast = Vec::new();
+        let invalid_fast = validate_files(&parse_args(&args(&list), |_| None).unwrap(), &mut fail_fast, &mut io::sink()).unwrap();
+        for (name, _) in files {
+            fs::remove_file(name).unwrap();
+        }
 
-        // Missing comma between array elements
-        let err = parse_json(r#"{"arr": [1 2]}"#).unwrap_err();
-        assert_eq!(err, "Expected ',' or ']'");
+        let report_all = String::from_utf8(report_all).unwrap();
+        let lines: Vec<&str> = report_all.lines().collect();
+        assert_eq!(invalid, 3);
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "test_multi_1.json: Valid JSON");
+        assert!(lines[1].starts_with("test_multi_2.json: Invalid JSON: "));
+        assert!(lines[4].starts_with("test_multi_missing.json: Error reading file: "));
+        assert_eq!(lines[5], "3 of 5 files invalid");
+
+        assert_eq!(invalid_fast, 1);
+        assert_eq!(String::from_utf8(fail_fast).unwrap().lines().count(), 2);
+    }
 
-        // Unclosed array
-        let err = parse_json(r#"{"arr": [1, 2"#).unwrap_err();
-        assert_eq!(err, "Unexpected end of input");
+    #[test]
+    fn test_strip_comments() {
+        let jsonc = "{\n  // where to listen\n  \"port\": 8080, /* default */\n  \"hosts\": [\"a\", \"b\"]\n}";
+        let config = parse_args(&args(&["json_parser", "--strip-comments", "in.jsonc"]), |_| None).unwrap();
+        assert!(parse(jsonc).is_err());
+
+        let output = render(&config, &parse_document(&config, jsonc, &mut io::sink()).unwrap().unwrap()).unwrap();
+        assert_eq!(output, r#"{"port":8080,"hosts":["a","b"]}"#);
+        assert!(parse(&output).is_ok());
     }
 
     #[test]
-    fn test_invalid_values() {
-        // Invalid boolean capitalization
-        let err = parse_json(r#"{"key": True}"#).unwrap_err();
-        assert_eq!(err, "Invalid identifier");
+    fn test_quiet_mode_prints_nothing() {
+        let valid = "test_quiet_valid.json";
+        let invalid = "test_quiet_invalid.json";
+        fs::write(valid, r#"{"a": 1}"#).unwrap();
+        fs::write(invalid, r#"{"a": }"#).unwrap();
+
+        let quiet_run_with = |list: &[&str]| {
+            let config = parse_args(&args(list), |_| None).unwrap();
+            assert!(config.quiet);
+            let (mut out, mut err) = (Vec::new(), Vec::new());
+            let ok = run(&config, &mut out, &mut err).unwrap();
+            (ok, out, err)
+        };
+        let quiet_run = |path: &str| quiet_run_with(&["json_parser", "--quiet", path]);
+        let (valid_ok, valid_out, _) = quiet_run(valid);
+        let (invalid_ok, invalid_out, invalid_err) = quiet_run(invalid);
+        // --errors-to-stderr still reports the parse error, on stderr
+        let (_, stderr_out, stderr_err) = quiet_run_with(&["json_parser", "--quiet", "--errors-to-stderr", invalid]);
+        let (_, valid_stderr_out, valid_stderr_err) = quiet_run_with(&["json_parser", "--quiet", "--errors-to-stderr", valid]);
+        let (_, files_out, files_err) = quiet_run_with(&["json_parser", "--quiet", "--errors-to-stderr", valid, invalid]);
+        fs::remove_file(valid).unwrap();
+        fs::remove_file(invalid).unwrap();
+
+        assert!(valid_ok);
+        assert!(!invalid_ok);
+        assert!(valid_out.is_empty());
+        assert!(invalid_out.is_empty());
+        assert!(invalid_err.is_empty());
+
+        assert!(stderr_out.is_empty());
+        assert_eq!(String::from_utf8(stderr_err).unwrap(), "Invalid JSON: Expected value at line 1, column 7\n");
+        assert!(valid_stderr_out.is_empty() && valid_stderr_err.is_empty());
+        assert!(files_out.is_empty());
+        assert!(String::from_utf8(files_err).unwrap().starts_with("test_quiet_invalid.json: Invalid JSON: Expected value"));
+    }
 
-        // Invalid number format
-        let err = parse_json(r#"{"key": 12.34.56}"#).unwrap_err();
-        assert_eq!(err, "Invalid number format");
+    #[test]
+    fn test_check_sorted() {
+        let unsorted = "test_check_sorted_unsorted.json";
+        let sorted = "test_check_sorted_sorted.json";
+        fs::write(unsorted, r#"{"a": 1, "b": {"list": [{"id": 1, "name": "x", "age": 3}]}}"#).unwrap();
+        fs::write(sorted, r#"{"a": 1, "b": {"c": [2, 1]}}"#).unwrap();
+
+        let check = |path: &str| {
+            let config = parse_args(&args(&["json_parser", "--check-sorted", path]), |_| None).unwrap();
+            let (mut out, mut err) = (Vec::new(), Vec::new());
+            let ok = run(&config, &mut out, &mut err).unwrap();
+            (ok, String::from_utf8(out).unwrap())
+        };
+        let (unsorted_ok, unsorted_out) = check(unsorted);
+        let (sorted_ok, sorted_out) = check(sorted);
+        fs::remove_file(unsorted).unwrap();
+        fs::remove_file(sorted).unwrap();
+
+        assert!(!unsorted_ok);
+        assert_eq!(unsorted_out, "Keys not sorted: `age` is out of order in the object at /b/list/0\n");
+        assert!(sorted_ok);
+        assert_eq!(sorted_out, "Valid JSON\n");
+        let root = parse(r#"{"b": 1, "a": 2}"#).unwrap();
+        assert_eq!(unsorted_keys_message(&root).unwrap(), "Keys not sorted: `a` is out of order in the root object");
+    }
 
-        // Single quotes instead of double quotes
-        let err = parse_json(r#"{'key': 'value'}"#).unwrap_err();
-        assert_eq!(err, "Invalid character in JSON");
+    #[test]
+    fn test_tree_outline() {
+        let value = parse(r#"{"name": "ana", "tags": ["a", {"x": null}], "meta": {"empty": []}, "n": 1}"#).unwrap();
+        let expected = "\
+object
+├─ name: \"ana\"
+├─ tags:
+│  ├─ [0]: \"a\"
+│  └─ [1]:
+│     └─ x: null
+├─ meta:
+│  └─ empty: []
+└─ n: 1";
+        assert_eq!(format_tree(&value), expected);
+        assert_eq!(format_tree(&parse("[]").unwrap()), "[]");
+        assert_eq!(format_tree(&parse("[true]").unwrap()), "array\n└─ [0]: true");
     }
 
     #[test]
-    fn test_invalid_objects() {
-        // Trailing comma in object
-        let err = parse_json(r#"{"key": "value",}"#).unwrap_err();
-        assert_eq!(err, "Trailing comma not allowed");
+    fn test_max_errors() {
+        let broken = "test_max_errors.json";
+        fs::write(broken, "[@, @, @, @, @]").unwrap();
+
+        let config = parse_args(&args(&["json_parser", "--one-line-errors", "--max-errors=2", broken]), |_| None).unwrap();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let ok = run(&config, &mut out, &mut err).unwrap();
+        fs::remove_file(broken).unwrap();
+
+        assert!(!ok);
+        assert_eq!(String::from_utf8(out).unwrap(), "\
+ERROR test_max_errors.json:1:2: Invalid character in JSON
+ERROR test_max_errors.json:1:5: Invalid character in JSON
+ERROR test_max_errors.json: ... and 3 more errors suppressed
+");
+        assert!(parse_args(&args(&["json_parser", "--max-errors=x"]), |_| None).is_err());
+        assert!(parse_args(&args(&["json_parser", "--max-errors=2", "a.json", "b.json"]), |_| None).is_err());
+        assert!(parse_args(&args(&["json_parser", "--explain", "a.json", "b.json"]), |_| None).is_err());
+    }
 
-        // Missing comma between properties
-        let err = parse_json(r#"{"key1": "value1" "key2": "value2"}"#).unwrap_err();
-        assert_eq!(err, "Expected ',' or '}'");
+    #[test]
+    fn test_explain_traces_to_stderr() {
+        let path = "test_explain.json";
+        fs::write(path, r#"{"k": null}"#).unwrap();
+
+        let config = parse_args(&args(&["json_parser", "--explain", path]), |_| None).unwrap();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let ok = run(&config, &mut out, &mut err).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert!(ok);
+        assert_eq!(String::from_utf8(out).unwrap(), "Valid JSON\n");
+        assert_eq!(String::from_utf8(err).unwrap(), "\
+lex: read '{' at 1:1
+lex: read string 'k' at 1:2
+lex: read ':' at 1:5
+lex: read null at 1:7
+lex: read '}' at 1:11
+parse: open object at 1:1
+parse: close object at 1:11
+");
+    }
 
-        // Duplicate keys (if implemented)
-        // let err = parse_json(r#"{"key": "value1", "key": "value2"}"#).unwrap_err();
-        // assert_eq!(err, "Duplicate key found");
+    #[test]
+    fn test_repair() {
+        let path = "test_repair.json";
+        fs::write(path, "{name: 'ana', tags: ['a', 'b',],").unwrap();
+        let config = parse_args(&args(&["json_parser", "--repair", path]), |_| None).unwrap();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let ok = run(&config, &mut out, &mut err).unwrap();
+        assert!(ok);
+        assert_eq!(String::from_utf8(out).unwrap(), "{\"name\":\"ana\",\"tags\":[\"a\",\"b\"]}\n");
+
+        fs::write(path, "[1, 2}").unwrap();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let ok = run(&config, &mut out, &mut err).unwrap();
+        fs::remove_file(path).unwrap();
+        assert!(!ok);
+        assert!(out.is_empty());
+        assert_eq!(String::from_utf8(err).unwrap(), "Could not repair: Closing bracket doesn't match the open container at line 1, column 6\n");
     }
 
     #[test]
-    fn test_empty_input() {
-        let err = parse_json("").unwrap_err();
-        assert_eq!(err, "Expected '{'");
+    fn test_token_profile() {
+        let tokens = Lexer::new(r#"{"a":[1,2],"b":true}"#).lex_tokens().unwrap();
+        assert_eq!(format_token_profile(&tokens), "\
+braces: 2
+brackets: 2
+strings: 2
+numbers: 2
+booleans: 1
+nulls: 0
+commas: 2
+colons: 2");
+
+        let path = "test_token_profile.json";
+        fs::write(path, "[null, null] ]").unwrap();
+        let config = parse_args(&args(&["json_parser", "--token-profile", path]), |_| None).unwrap();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let ok = run(&config, &mut out, &mut err).unwrap();
+        fs::remove_file(path).unwrap();
+        assert!(ok);
+        assert!(String::from_utf8(out).unwrap().starts_with("braces: 0\nbrackets: 3\n"));
     }
 
     #[test]
-    fn test_complex_invalid_cases() {
-        // Mixing array and object syntax
-        let err = parse_json(r#"{"arr": [}"#).unwrap_err();
-        assert_eq!(err, "Expected value");
-
-        // Nested invalid syntax
-        let err = parse_json(r#"{
-            "outer": {
-                "inner": {
-                    "key": value
-                }
-            }
-        }"#).unwrap_err();
-        assert_eq!(err, "Invalid identifier");
+    fn test_infer_schema() {
+        let record = parse(r#"{"id": 7, "name": "ana", "active": true, "manager": null, "tags": ["a", "b"], "address": {"city": "x"}, "scores": [1, "n/a"], "notes": []}"#).unwrap();
+        let schema = infer_schema(&record);
+        let type_at = |ptr: &str| pointer(&schema, ptr).cloned();
+        let string = |s: &str| Some(JsonValue::String(s.to_string()));
+
+        assert_eq!(type_at("/$schema"), string("http://json-schema.org/draft-07/schema#"));
+        assert_eq!(type_at("/type"), string("object"));
+        assert_eq!(type_at("/properties/id/type"), string("number"));
+        assert_eq!(type_at("/properties/name/type"), string("string"));
+        assert_eq!(type_at("/properties/active/type"), string("boolean"));
+        assert_eq!(type_at("/properties/manager/type"), string("null"));
+        assert_eq!(type_at("/properties/tags/items/type"), string("string"));
+        assert_eq!(type_at("/properties/address/properties/city/type"), string("string"));
+        assert_eq!(type_at("/properties/address/required"), Some(parse(r#"["city"]"#).unwrap()));
+        assert_eq!(type_at("/properties/scores/items"), Some(parse(r#"{"anyOf": [{"type": "number"}, {"type": "string"}]}"#).unwrap()));
+        assert_eq!(type_at("/properties/notes"), Some(parse(r#"{"type": "array"}"#).unwrap()));
+        assert_eq!(type_at("/required/0"), string("id"));
+        assert_eq!(type_at("/required/7"), string("notes"));
+
+        let config = Config { infer_schema: true, ..parse_args(&args(&["json_parser"]), |_| None).unwrap() };
+        assert_eq!(render(&config, &parse("[1, 2]").unwrap()).unwrap(), to_string_pretty(&parse(r#"{"$schema": "http://json-schema.org/draft-07/schema#", "type": "array", "items": {"type": "number"}}"#).unwrap()));
     }
 
     #[test]
-    fn test_boundary_cases() {
-        // Test very long string (this should still work)
-        let long_string = format!(r#"{{"key": "{}"}}"#, "a".repeat(1000));
-        assert!(parse_json(&long_string).is_ok());
-        
-        // Test nesting limit (should fail gracefully at extreme depths)
-        let too_deep = "{".repeat(1000) + "}".repeat(1000).as_str();
-        assert!(parse_json(&too_deep).is_err());
-    }
-    
-    // Add a new test specifically for reasonable nesting depths
+    fn test_bom_out() {
+        let path = "test_bom_out.json";
+        fs::write(path, r#"{"a": [1]}"#).unwrap();
+        let output = |flags: &[&str]| {
+            let mut argv = vec!["json_parser"];
+            argv.extend(flags);
+            argv.push(path);
+            let config = parse_args(&args(&argv), |_| None).unwrap();
+            let (mut out, mut err) = (Vec::new(), Vec::new());
+            assert!(run(&config, &mut out, &mut err).unwrap());
+            out
+        };
+        let with_bom = output(&["--pretty", "--bom-out"]);
+        let without = output(&["--pretty"]);
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(&with_bom[..3], UTF8_BOM);
+        assert_eq!(&with_bom[3..], &without[..]);
+        assert_eq!(String::from_utf8(without).unwrap(), "{\n  \"a\": [\n    1\n  ]\n}\n");
+        assert!(parse_args(&args(&["json_parser", "--bom-out", path]), |_| None).is_err());
+    }
+
     #[test]
-    fn test_nested_depth() {
-        // Test reasonable nesting (should pass)
-        let nested_10 = r#"{
-            "l1": {
-                "l2": {
-                    "l3": {
-                        "l4": {
-                            "l5": {
-                                "l6": {
-                                    "l7": {
-                                        "l8": {
-                                            "l9": {
-                                                "l10": {}
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }"#;
-        assert!(parse_json(nested_10).is_ok());
+    fn test_time() {
+        assert_eq!(format_timing(2_000_000, Duration::from_millis(500)), "Parsed 2000000 bytes in 500.000 ms (4.0 MB/s)");
+        assert_eq!(format_timing(10, Duration::ZERO), "Parsed 10 bytes in 0.000 ms (- MB/s)");
+
+        let path = "test_time.json";
+        let check = |content: &str| {
+            fs::write(path, content).unwrap();
+            let config = parse_args(&args(&["json_parser", "--time", path]), |_| None).unwrap();
+            let (mut out, mut err) = (Vec::new(), Vec::new());
+            let ok = run(&config, &mut out, &mut err).unwrap();
+            (ok, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+        };
+        let (valid_ok, valid_out, valid_err) = check(r#"{"a": [1, 2]}"#);
+        let (invalid_ok, invalid_out, invalid_err) = check("[1,]");
+        fs::remove_file(path).unwrap();
+
+        assert!(valid_ok);
+        assert_eq!(valid_out, "Valid JSON\n");
+        assert!(valid_err.starts_with("Parsed 13 bytes in ") && valid_err.ends_with(" MB/s)\n"), "{}", valid_err);
+        assert!(!invalid_ok);
+        assert!(invalid_out.starts_with("Invalid JSON: Trailing comma not allowed"));
+        assert!(invalid_err.starts_with("Parsed 4 bytes in "));
+        assert!(parse_args(&args(&["json_parser", "--time", "a.json", "b.json"]), |_| None).is_err());
     }
-}
\ No newline at end of file
+}
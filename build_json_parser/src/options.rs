@@ -0,0 +1,84 @@
+/// Switches that relax or tighten the JSON grammar.
+///
+/// The default is standard JSON (RFC 8259).
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Accept `0x`, `0b` and `0o` prefixed integer literals.
+    pub allow_radix_prefixes: bool,
+    /// Reject `{}` and `[]` anywhere in the document.
+    pub forbid_empty_containers: bool,
+    /// Treat `// line` and `/* block */` comments as whitespace (JSONC).
+    pub allow_comments: bool,
+    /// When comments are allowed, keep them (see `Lexer::take_comments`)
+    /// instead of discarding them.
+    pub collect_comments: bool,
+    /// Reject strings whose UTF-8 encoding is longer than this many bytes
+    /// (quotes not included).
+    pub max_string_bytes: Option<usize>,
+    /// Store every number in one canonical form. Numbers are kept as `f64`,
+    /// so `1`, `1.0` and `1e0` already compare and serialize the same; this
+    /// also folds `-0` into `0`.
+    pub canonicalize_numbers: bool,
+    /// Normalize scalars while parsing: numbers are stored as with
+    /// `canonicalize_numbers`, and `parse_shared` gives equal strings (keys
+    /// and values alike) one shared allocation.
+    pub normalize_on_parse: bool,
+    /// Have `parse_shared` give equal object keys one shared allocation,
+    /// as `normalize_on_parse` does, but leave string values alone.
+    pub intern_keys: bool,
+    /// Record a line for every token read and every container opened or
+    /// closed (see `parse_with_trace`).
+    pub trace: bool,
+    /// Reject strings containing Unicode noncharacters (U+FDD0 to U+FDEF,
+    /// and the last two code points of every plane, such as U+FFFE and
+    /// U+FFFF), whether written literally or as `\u` escapes.
+    pub reject_noncharacters: bool,
+    /// What to do when an object repeats a key.
+    pub duplicate_keys: DuplicateKeyPolicy,
+    /// Whether commas between array elements and object members are
+    /// required.
+    pub commas: CommaPolicy,
+}
+
+/// How the parser handles an object that repeats a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeyPolicy {
+    /// Keep every entry in source order.
+    #[default]
+    KeepAll,
+    /// Fail with "Duplicate key", pointing at the repeated key.
+    Reject,
+    /// Keep the first entry and drop later ones.
+    KeepFirst,
+    /// Keep the last value, in the place of the first entry.
+    KeepLast,
+    /// Replace the entry with an array of all its values, in source order,
+    /// in the place of the first entry. A key that appears once is left
+    /// alone, so its value may still be an array of its own.
+    Combine,
+}
+
+/// Whether the parser insists on commas between values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CommaPolicy {
+    /// Standard JSON: a missing comma is "Expected ',' or ']'" (or `'}'`).
+    #[default]
+    Required,
+    /// Accept values and members separated by nothing but whitespace, as
+    /// in hand-written data with one entry per line. Commas that are there
+    /// must still be in the right places, and trailing commas are still
+    /// rejected.
+    AllowMissing,
+}
+
+/// How `to_string_with` lays out its output.
+///
+/// The default is compact output with no trailing newline, the same as
+/// `to_string`.
+#[derive(Debug, Clone, Default)]
+pub struct FormatOptions {
+    /// Indent like `to_string_pretty` instead of writing compactly.
+    pub pretty: bool,
+    /// End the output with a newline.
+    pub final_newline: bool,
+}
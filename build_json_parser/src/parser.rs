@@ -0,0 +1,558 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::lexer::{describe, Position, Token};
+use crate::options::{CommaPolicy, DuplicateKeyPolicy, ParseOptions};
+use crate::value::JsonValue;
+
+/// Whether the parser builds a `JsonValue` or only checks the grammar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Check the input without keeping anything: strings aren't copied and
+    /// no arrays or objects are allocated. Every parse method returns
+    /// `JsonValue::Null` on success.
+    ValidateOnly,
+    /// Build the full value.
+    #[default]
+    BuildValue,
+}
+
+pub struct Parser {
+    tokens: Vec<Token>,
+    positions: Vec<Position>,
+    position: usize,
+    options: ParseOptions,
+    mode: ParseMode,
+    trace: Vec<String>,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Self::with_positions(tokens, Vec::new())
+    }
+
+    // Takes the source positions from Lexer::lex_with_positions so errors
+    // can say where they happened.
+    pub fn with_positions(tokens: Vec<Token>, positions: Vec<Position>) -> Self {
+        Self::with_options(tokens, positions, ParseOptions::default())
+    }
+
+    pub fn with_options(tokens: Vec<Token>, positions: Vec<Position>, options: ParseOptions) -> Self {
+        Parser {
+            tokens,
+            positions,
+            position: 0,
+            options,
+            mode: ParseMode::BuildValue,
+            trace: Vec::new(),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: ParseMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    fn building(&self) -> bool {
+        self.mode == ParseMode::BuildValue
+    }
+
+    // Source position of the next unconsumed token (or end of input)
+    pub fn current_position(&self) -> Option<Position> {
+        self.positions.get(self.position).copied()
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    // Records a trace line about the token at `index`
+    fn note(&mut self, what: &str, index: usize) {
+        if !self.options.trace {
+            return;
+        }
+        match self.positions.get(index) {
+            Some(position) => self.trace.push(format!("parse: {} at {}:{}", what, position.line, position.column)),
+            None => self.trace.push(format!("parse: {}", what)),
+        }
+    }
+
+    // Records why parsing stopped, naming the token it stopped at
+    pub(crate) fn note_error(&mut self, message: &str) {
+        let found = self.peek().map_or("end of input".into(), describe);
+        self.note(&format!("{}, found {}", message, found), self.position);
+    }
+
+    // Hands over the trace lines recorded so far (see ParseOptions::trace)
+    pub fn take_trace(&mut self) -> Vec<String> {
+        core::mem::take(&mut self.trace)
+    }
+
+    fn advance(&mut self) {
+        self.position += 1
+    }
+
+    // Returns true once every token has been consumed
+    pub fn is_at_end(&self) -> bool {
+        self.position >= self.tokens.len()
+    }
+
+    pub fn parse_value(&mut self) -> Result<JsonValue, &'static str> {
+        let value = match self.peek() {
+            Some(Token::LeftBrace) => {
+                self.note("open object", self.position);
+                let object = self.parse_object()?;
+                self.note("close object", self.position - 1);
+                return Ok(object);
+            }
+            Some(Token::LeftBracket) => {
+                self.note("open array", self.position);
+                let array = self.parse_array()?;
+                self.note("close array", self.position - 1);
+                return Ok(array);
+            }
+            Some(Token::String(s)) if self.building() => JsonValue::String(s.clone()),
+            Some(Token::String(_)) => JsonValue::Null,
+            Some(Token::Number(n)) => JsonValue::Number(*n),
+            Some(Token::Boolean(b)) => JsonValue::Boolean(*b),
+            Some(Token::Null) => JsonValue::Null,
+            _ => return Err("Expected value"),
+        };
+        self.advance();
+        Ok(value)
+    }
+
+    pub fn parse_array(&mut self) -> Result<JsonValue, &'static str> {
+        // Consume the opening bracket
+        match self.peek() {
+            Some(Token::LeftBracket) => self.advance(),
+            _ => return Err("Expected '['"),
+        }
+
+        let mut elements = Vec::new();
+        let mut first = true;
+        while let Some(token) = self.peek() {
+            match token {
+                // Case 1: We see a closing bracket and we're at the first position
+                Token::RightBracket if first => {
+                    if self.options.forbid_empty_containers {
+                        return Err("Empty container not allowed");
+                    }
+                    self.advance();
+                    return Ok(JsonValue::Array(elements))  // Empty array [] is valid
+                }
+
+                // Case 2: We see a closing bracket after some values
+                Token::RightBracket => {
+                    self.advance();
+                    return Ok(JsonValue::Array(elements));  // Array is properly closed
+                }
+
+                // Case 3: We see a comma after a value (not first)
+                Token::Comma if !first => {
+                    self.advance();
+                    // After a comma, check for trailing comma
+                    if let Some(Token::RightBracket) = self.peek() {
+                        // [1, 2,] is invalid
+                        return Err("Trailing comma not allowed")
+                    }
+                }
+
+                // Case 4: We're not at first item and don't see comma or rightbracket
+                _ if !first && self.options.commas == CommaPolicy::Required => return Err("Expected ',' or ']'"),
+
+                // Case 5: Any other token, continue processing
+                _ => {}
+            }
+            let element = self.parse_value()?;
+            if self.building() {
+                elements.push(element);
+            }
+            first = false;
+        }
+        Err("Unexpected end of input")
+    }
+
+    pub fn parse_object(&mut self) -> Result<JsonValue, &'static str> {
+        //Expect opening brace
+        match self.peek() {
+            Some(Token::LeftBrace) => self.advance(),
+            _ => return Err("Expected '{'"),
+        }
+
+        let policy = self.options.duplicate_keys;
+        let mut entries: Vec<(String, JsonValue)> = Vec::new();
+        // Keys seen so far under DuplicateKeyPolicy::Reject
+        let mut seen = BTreeSet::new();
+        // Index in `entries` of each key, under KeepFirst, KeepLast and Combine
+        let mut indices = BTreeMap::new();
+        // Entries already turned into arrays under DuplicateKeyPolicy::Combine
+        let mut combined = BTreeSet::new();
+        let mut first = true;
+        while let Some(token) = self.peek() {
+            match token {
+                // Case 1: We see a closing brace and we're at the first position
+                Token::RightBrace if first => {
+                    if self.options.forbid_empty_containers {
+                        return Err("Empty container not allowed");
+                    }
+                    self.advance();
+                    return Ok(JsonValue::Object(entries)); // Empty object {} is valid
+                }
+
+                // Case 2: We see a closing brace after some key-value pairs
+                Token::RightBrace => {
+                    self.advance();
+                    return Ok(JsonValue::Object(entries));  // Object is properly closed
+                }
+
+                // Case 3: We see a comma after a key-value pair (not first)
+                Token::Comma if !first => {
+                    self.advance();
+                    // After a comma, check for trailing comma
+                    if let Some(Token::RightBrace) = self.peek() {
+                        // {"key": "value",} is invalid
+                        return Err("Trailing comma not allowed")
+                    }
+                }
+
+                // Case 4: We're not at first item and don't see comma or rightbrace
+                _ if !first && self.options.commas == CommaPolicy::Required => {
+                    // If we've already processed a pair but don't see
+                    // a comma or closing brace, it's an error
+                    return Err("Expected ',' or '}'")
+                }
+
+                // Case 5: Any other token, continue processing
+                _ => {}
+            }
+
+            // Parse key
+            let key = match self.peek() {
+                Some(Token::String(key)) if self.building() => key.clone(),
+                Some(Token::String(key)) if policy == DuplicateKeyPolicy::Reject => key.clone(),
+                Some(Token::String(_)) => String::new(),
+                _ => return Err("Expected string key"),
+            };
+            if policy == DuplicateKeyPolicy::Reject && !seen.insert(key.clone()) {
+                return Err("Duplicate key");
+            }
+            self.advance();
+
+            // Parse colon
+            match self.peek() {
+                Some(Token::Colon) => self.advance(),
+                _ => return Err("Expected ':'"),
+            }
+
+            // Parse value (now recursive)
+            let value = self.parse_value()?;
+            if self.building() {
+                let existing = match policy {
+                    DuplicateKeyPolicy::KeepAll | DuplicateKeyPolicy::Reject => None,
+                    _ => indices.get(&key).copied(),
+                };
+                match (policy, existing) {
+                    (DuplicateKeyPolicy::KeepAll | DuplicateKeyPolicy::Reject, _) => entries.push((key, value)),
+                    (_, None) => {
+                        indices.insert(key.clone(), entries.len());
+                        entries.push((key, value));
+                    }
+                    (DuplicateKeyPolicy::KeepLast, Some(index)) => entries[index].1 = value,
+                    (DuplicateKeyPolicy::Combine, Some(index)) => {
+                        let slot = &mut entries[index].1;
+                        if combined.insert(index) {
+                            *slot = JsonValue::Array(vec![slot.take(), value]);
+                        } else if let JsonValue::Array(values) = slot {
+                            values.push(value);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            first = false;
+        }
+
+        Err("Unexpected end of input")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn parse_json(input: &str) -> Result<JsonValue, String> {
+        let mut lexer = Lexer::new(input);
+        let tokens = lexer.lex_tokens().map_err(|e| e.message().to_string())?;
+        let mut parser = Parser::new(tokens);
+        parser.parse_object().map_err(|e| e.to_string())
+    }
+
+    // Tests for Valid JSON
+    #[test]
+    fn test_empty_object() {
+        assert!(parse_json("{}").is_ok());
+    }
+
+    #[test]
+    fn test_basic_types() {
+        // Test all basic JSON types
+        assert!(parse_json(r#"{
+            "string": "hello_world",
+            "number": 42,
+            "float": 3.14,
+            "negative": -123,
+            "boolean_true": true,
+            "boolean_false": false,
+            "null_value": null
+        }"#).is_ok());
+    }
+
+    #[test]
+    fn test_nested_structures() {
+        assert!(parse_json(r#"{
+            "empty_object": {},
+            "empty_array": [],
+            "nested_object": {"key": "value"},
+            "nested_array": ["item"],
+            "deep_nesting": {
+                "level1": {
+                    "level2": {
+                        "level3": {}
+                    }
+                }
+            }
+        }"#).is_ok());
+    }
+
+    #[test]
+    fn test_array_variations() {
+        assert!(parse_json(r#"{
+            "mixed_array": [1, "string", true, null, {"key": "value"}, [1, 2, 3]],
+            "number_array": [1, 2, 3, 4, 5],
+            "nested_arrays": [[], [1], [1, [2, [3]]]],
+            "object_array": [{"k1": "v1"}, {"k2": "v2"}]
+        }"#).is_ok());
+    }
+
+    #[test]
+    fn test_whitespace_handling() {
+        // Test various whitespace scenarios
+        assert!(parse_json(r#"{"key" : "value"}"#).is_ok());
+        assert!(parse_json("{\n\t\"key\":\"value\"\n}").is_ok());
+        assert!(parse_json("{ \r\n \t }").is_ok());
+    }
+
+    #[test]
+    fn test_builds_values() {
+        let value = parse_json(r#"{"name": "rowc", "tags": [1, true, null]}"#).unwrap();
+        assert_eq!(value, JsonValue::Object(vec![
+            ("name".to_string(), JsonValue::String("rowc".to_string())),
+            ("tags".to_string(), JsonValue::Array(vec![
+                JsonValue::Number(1.0),
+                JsonValue::Boolean(true),
+                JsonValue::Null,
+            ])),
+        ]));
+    }
+
+    // Tests for Invalid JSON
+    #[test]
+    fn test_invalid_syntax() {
+        // Missing closing brace
+        let err = parse_json(r#"{"key": "value""#).unwrap_err();
+        assert_eq!(err, "Unexpected end of input");
+
+        // Missing quotes around key
+        let err = parse_json(r#"{key: "value"}"#).unwrap_err();
+        assert_eq!(err, "Invalid identifier");
+
+        // Missing colon
+        let err = parse_json(r#"{"key" "value"}"#).unwrap_err();
+        assert_eq!(err, "Expected ':'");
+    }
+
+    #[test]
+    fn test_invalid_arrays() {
+        // Trailing comma in array
+        let err = parse_json(r#"{"arr": [1, 2, ]}"#).unwrap_err();
+        assert_eq!(err, "Trailing comma not allowed");
+
+        // Missing comma between array elements
+        let err = parse_json(r#"{"arr": [1 2]}"#).unwrap_err();
+        assert_eq!(err, "Expected ',' or ']'");
+
+        // Unclosed array
+        let err = parse_json(r#"{"arr": [1, 2"#).unwrap_err();
+        assert_eq!(err, "Unexpected end of input");
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        // Invalid boolean capitalization
+        let err = parse_json(r#"{"key": True}"#).unwrap_err();
+        assert_eq!(err, "Invalid identifier");
+
+        // Invalid number format
+        let err = parse_json(r#"{"key": 12.34.56}"#).unwrap_err();
+        assert_eq!(err, "Invalid number format");
+
+        // Single quotes instead of double quotes
+        let err = parse_json(r#"{'key': 'value'}"#).unwrap_err();
+        assert_eq!(err, "Invalid character in JSON");
+    }
+
+    #[test]
+    fn test_invalid_objects() {
+        // Trailing comma in object
+        let err = parse_json(r#"{"key": "value",}"#).unwrap_err();
+        assert_eq!(err, "Trailing comma not allowed");
+
+        // Missing comma between properties
+        let err = parse_json(r#"{"key1": "value1" "key2": "value2"}"#).unwrap_err();
+        assert_eq!(err, "Expected ',' or '}'");
+
+        // Duplicate keys (if implemented)
+        // let err = parse_json(r#"{"key": "value1", "key": "value2"}"#).unwrap_err();
+        // assert_eq!(err, "Duplicate key found");
+    }
+
+    #[test]
+    fn test_empty_input() {
+        let err = parse_json("").unwrap_err();
+        assert_eq!(err, "Expected '{'");
+    }
+
+    #[test]
+    fn test_complex_invalid_cases() {
+        // Mixing array and object syntax
+        let err = parse_json(r#"{"arr": [}"#).unwrap_err();
+        assert_eq!(err, "Expected value");
+
+        // Nested invalid syntax
+        let err = parse_json(r#"{
+            "outer": {
+                "inner": {
+                    "key": value
+                }
+            }
+        }"#).unwrap_err();
+        assert_eq!(err, "Invalid identifier");
+    }
+
+    #[test]
+    fn test_boundary_cases() {
+        // Test very long string (this should still work)
+        let long_string = format!(r#"{{"key": "{}"}}"#, "a".repeat(1000));
+        assert!(parse_json(&long_string).is_ok());
+        
+        // Test nesting limit (should fail gracefully at extreme depths)
+        let too_deep = "{".repeat(1000) + "}".repeat(1000).as_str();
+        assert!(parse_json(&too_deep).is_err());
+    }
+    
+    // Add a new test specifically for reasonable nesting depths
+    #[test]
+    fn test_nested_depth() {
+        // Test reasonable nesting (should pass)
+        let nested_10 = r#"{
+            "l1": {
+                "l2": {
+                    "l3": {
+                        "l4": {
+                            "l5": {
+                                "l6": {
+                                    "l7": {
+                                        "l8": {
+                                            "l9": {
+                                                "l10": {}
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }"#;
+        assert!(parse_json(nested_10).is_ok());
+    }
+
+    fn parse_with_policy(input: &str, policy: DuplicateKeyPolicy) -> Result<JsonValue, crate::ParseError> {
+        let options = ParseOptions { duplicate_keys: policy, ..ParseOptions::default() };
+        crate::parse_with_options(input, &options)
+    }
+
+    #[test]
+    fn test_duplicate_key_policies() {
+        let input = r#"{"a":1,"a":2}"#;
+        let object = |entries: &[(&str, JsonValue)]| {
+            JsonValue::Object(entries.iter().map(|(k, v)| (k.to_string(), v.clone())).collect())
+        };
+        let (one, two) = (JsonValue::Number(1.0), JsonValue::Number(2.0));
+
+        assert_eq!(parse_with_policy(input, DuplicateKeyPolicy::KeepAll).unwrap(), object(&[("a", one.clone()), ("a", two.clone())]));
+        assert_eq!(parse_with_policy(input, DuplicateKeyPolicy::KeepFirst).unwrap(), object(&[("a", one.clone())]));
+        assert_eq!(parse_with_policy(input, DuplicateKeyPolicy::KeepLast).unwrap(), object(&[("a", two.clone())]));
+        assert_eq!(
+            parse_with_policy(input, DuplicateKeyPolicy::Combine).unwrap(),
+            object(&[("a", JsonValue::Array(vec![one, two]))])
+        );
+
+        let err = parse_with_policy(input, DuplicateKeyPolicy::Reject).unwrap_err();
+        assert_eq!(err.message(), "Duplicate key");
+        assert_eq!(err.position().map(|p| p.column), Some(8));
+    }
+
+    #[test]
+    fn test_duplicate_key_policies_nested_and_repeated() {
+        let input = r#"{"a": [0], "b": {"c": 1, "c": 2}, "a": 1, "a": [2]}"#;
+        let combined = parse_with_policy(input, DuplicateKeyPolicy::Combine).unwrap();
+        assert_eq!(combined, crate::parse(r#"{"a": [[0], 1, [2]], "b": {"c": [1, 2]}}"#).unwrap());
+        let last = parse_with_policy(input, DuplicateKeyPolicy::KeepLast).unwrap();
+        assert_eq!(last, crate::parse(r#"{"a": [2], "b": {"c": 2}}"#).unwrap());
+
+        // Rejection also works when only validating
+        let options = ParseOptions { duplicate_keys: DuplicateKeyPolicy::Reject, ..ParseOptions::default() };
+        let tokens = Lexer::new(input).lex_tokens().unwrap();
+        let mut parser = Parser::with_options(tokens, Vec::new(), options).with_mode(ParseMode::ValidateOnly);
+        assert_eq!(parser.parse_value(), Err("Duplicate key"));
+        assert!(parse_with_policy(r#"{"a": {"a": 1}}"#, DuplicateKeyPolicy::Reject).is_ok());
+    }
+
+    #[test]
+    fn test_duplicate_keys_in_large_object() {
+        // Looking keys up by scanning the entries would make this quadratic
+        let count = 100_000;
+        let members: Vec<String> = (0..count).map(|i| format!(r#""k{}": {}"#, i, i)).collect();
+        let input = format!(r#"{{{}, "k0": -1}}"#, members.join(", "));
+
+        let last = parse_with_policy(&input, DuplicateKeyPolicy::KeepLast).unwrap();
+        let JsonValue::Object(entries) = &last else { panic!("expected an object") };
+        assert_eq!(entries.len(), count);
+        assert_eq!(entries[0], ("k0".to_string(), JsonValue::Number(-1.0)));
+        assert_eq!(entries[count - 1], (format!("k{}", count - 1), JsonValue::Number((count - 1) as f64)));
+    }
+
+    #[test]
+    fn test_comma_policy() {
+        let lenient = ParseOptions { commas: CommaPolicy::AllowMissing, ..ParseOptions::default() };
+        assert_eq!(crate::parse_with_options(r#"{"a":1 "b":2}"#, &lenient).unwrap(), crate::parse(r#"{"a": 1, "b": 2}"#).unwrap());
+        assert_eq!(crate::parse(r#"{"a":1 "b":2}"#).unwrap_err().message(), "Expected ',' or '}'");
+
+        let lines = "[\n  {\"id\": 1}\n  {\"id\": 2, \"tags\": [\"x\" \"y\"]}\n]";
+        assert_eq!(crate::parse_with_options(lines, &lenient).unwrap(), crate::parse(r#"[{"id": 1}, {"id": 2, "tags": ["x", "y"]}]"#).unwrap());
+
+        // Commas that are present still have to be in the right places
+        assert_eq!(crate::parse_with_options("[1, 2,]", &lenient).unwrap_err().message(), "Trailing comma not allowed");
+        assert_eq!(crate::parse_with_options("[1,, 2]", &lenient).unwrap_err().message(), "Expected value");
+        assert_eq!(crate::parse_with_options(r#"{"a" 1}"#, &lenient).unwrap_err().message(), "Expected ':'");
+        assert!(crate::parse_collecting(r#"{"a":1 "b":[2 3]}"#, &lenient, 5).is_ok());
+    }
+}
@@ -0,0 +1,228 @@
+//! JSON Patch (RFC 6902).
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::error::ParseError;
+use crate::pointer::{array_index, pointer, resolve_mut, segments};
+use crate::value::JsonValue;
+
+/// Applies a JSON Patch: an array of `add`, `remove`, `replace`, `move`,
+/// `copy` and `test` operations, each addressed with JSON Pointers.
+///
+/// The patch is all or nothing. If any operation fails, including a `test`
+/// whose value doesn't match, `doc` is left unchanged. Values are compared
+/// with `==`, so objects must have their keys in the same order to match.
+pub fn apply_patch(doc: &mut JsonValue, patch: &JsonValue) -> Result<(), ParseError> {
+    let operations = match patch {
+        JsonValue::Array(operations) => operations,
+        _ => return Err(invalid("Patch must be an array of operations")),
+    };
+
+    let mut patched = doc.clone();
+    for operation in operations {
+        apply_operation(&mut patched, operation)?;
+    }
+    *doc = patched;
+    Ok(())
+}
+
+fn invalid(message: &str) -> ParseError {
+    ParseError::Validation(message.to_string())
+}
+
+fn not_found(ptr: &str) -> ParseError {
+    ParseError::Validation(format!("Path not found: {}", ptr))
+}
+
+// A string member of an operation object, e.g. `path` or `from`
+fn member<'a>(operation: &'a JsonValue, key: &str) -> Result<&'a str, ParseError> {
+    match operation.get(key) {
+        Some(JsonValue::String(s)) => Ok(s),
+        _ => Err(ParseError::Validation(format!("Patch operation is missing `{}`", key))),
+    }
+}
+
+fn apply_operation(doc: &mut JsonValue, operation: &JsonValue) -> Result<(), ParseError> {
+    if !matches!(operation, JsonValue::Object(_)) {
+        return Err(invalid("Patch operation must be an object"));
+    }
+    let op = member(operation, "op")?;
+    let path = member(operation, "path")?;
+    let value = || operation.get("value").cloned().ok_or_else(|| invalid("Patch operation is missing `value`"));
+
+    match op {
+        "add" => add(doc, path, value()?),
+        "remove" => remove(doc, path).map(|_| ()),
+        "replace" => {
+            let slot = resolve_mut(doc, &split(path)?).ok_or_else(|| not_found(path))?;
+            *slot = value()?;
+            Ok(())
+        }
+        "move" => {
+            let from = member(operation, "from")?;
+            if from != path && path.starts_with(from) && path[from.len()..].starts_with('/') {
+                return Err(ParseError::Validation(format!("Cannot move {} into its own child {}", from, path)));
+            }
+            let moved = remove(doc, from)?;
+            add(doc, path, moved)
+        }
+        "copy" => {
+            let from = member(operation, "from")?;
+            let copied = pointer(doc, from).cloned().ok_or_else(|| not_found(from))?;
+            add(doc, path, copied)
+        }
+        "test" => {
+            let expected = value()?;
+            match pointer(doc, path) {
+                Some(actual) if *actual == expected => Ok(()),
+                _ => Err(ParseError::Validation(format!("Test failed at {}", path))),
+            }
+        }
+        _ => Err(ParseError::Validation(format!("Unknown patch op `{}`", op))),
+    }
+}
+
+fn split(ptr: &str) -> Result<Vec<String>, ParseError> {
+    segments(ptr).ok_or_else(|| ParseError::Validation(format!("Invalid pointer: {}", ptr)))
+}
+
+// Splits off the last segment and resolves the container holding it
+fn parent<'a>(doc: &'a mut JsonValue, ptr: &str) -> Result<(&'a mut JsonValue, String), ParseError> {
+    let mut segments = split(ptr)?;
+    let last = segments.pop().ok_or_else(|| ParseError::Validation(format!("Cannot modify the root with {}", ptr)))?;
+    let container = resolve_mut(doc, &segments).ok_or_else(|| not_found(ptr))?;
+    Ok((container, last))
+}
+
+// Adds or overwrites an object member, or inserts into an array (`-`
+// appends). An empty path replaces the whole document.
+fn add(doc: &mut JsonValue, ptr: &str, value: JsonValue) -> Result<(), ParseError> {
+    if ptr.is_empty() {
+        *doc = value;
+        return Ok(());
+    }
+    let (container, last) = parent(doc, ptr)?;
+    match container {
+        JsonValue::Object(entries) => match entries.iter_mut().rev().find(|(k, _)| *k == last) {
+            Some((_, slot)) => *slot = value,
+            None => entries.push((last, value)),
+        },
+        JsonValue::Array(elements) if last == "-" => elements.push(value),
+        JsonValue::Array(elements) => match array_index(&last) {
+            Some(index) if index <= elements.len() => elements.insert(index, value),
+            _ => return Err(not_found(ptr)),
+        },
+        _ => return Err(not_found(ptr)),
+    }
+    Ok(())
+}
+
+// Removes and returns the value at `ptr`. Every occurrence of a duplicated
+// key is removed; the last one is returned.
+fn remove(doc: &mut JsonValue, ptr: &str) -> Result<JsonValue, ParseError> {
+    let (container, last) = parent(doc, ptr)?;
+    match container {
+        JsonValue::Object(entries) => {
+            let mut removed = None;
+            entries.retain_mut(|(k, v)| {
+                if *k != last {
+                    return true;
+                }
+                removed = Some(core::mem::replace(v, JsonValue::Null));
+                false
+            });
+            removed.ok_or_else(|| not_found(ptr))
+        }
+        JsonValue::Array(elements) => match array_index(&last) {
+            Some(index) if index < elements.len() => Ok(elements.remove(index)),
+            _ => Err(not_found(ptr)),
+        },
+        _ => Err(not_found(ptr)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn patched(doc: &str, patch: &str) -> Result<JsonValue, ParseError> {
+        let mut doc = parse(doc).unwrap();
+        apply_patch(&mut doc, &parse(patch).unwrap())?;
+        Ok(doc)
+    }
+
+    #[test]
+    fn test_add() {
+        let doc = patched(r#"{"a": [1, 3]}"#, r#"[
+            {"op": "add", "path": "/a/1", "value": 2},
+            {"op": "add", "path": "/a/-", "value": 4},
+            {"op": "add", "path": "/b", "value": {"c": true}}
+        ]"#).unwrap();
+        assert_eq!(doc, parse(r#"{"a": [1, 2, 3, 4], "b": {"c": true}}"#).unwrap());
+
+        let err = patched("[1]", r#"[{"op": "add", "path": "/5", "value": 0}]"#).unwrap_err();
+        assert_eq!(err.message(), "Path not found: /5");
+    }
+
+    #[test]
+    fn test_remove() {
+        let doc = patched(r#"{"a": [1, 2], "b": 1}"#, r#"[
+            {"op": "remove", "path": "/a/0"},
+            {"op": "remove", "path": "/b"}
+        ]"#).unwrap();
+        assert_eq!(doc, parse(r#"{"a": [2]}"#).unwrap());
+
+        let err = patched("{}", r#"[{"op": "remove", "path": "/x"}]"#).unwrap_err();
+        assert_eq!(err.message(), "Path not found: /x");
+    }
+
+    #[test]
+    fn test_replace() {
+        let doc = patched(r#"{"a": {"b": 1}}"#, r#"[{"op": "replace", "path": "/a/b", "value": [null]}]"#).unwrap();
+        assert_eq!(doc, parse(r#"{"a": {"b": [null]}}"#).unwrap());
+
+        let doc = patched(r#"{"a": 1}"#, r#"[{"op": "replace", "path": "", "value": 2}]"#).unwrap();
+        assert_eq!(doc, JsonValue::Number(2.0));
+    }
+
+    #[test]
+    fn test_move() {
+        let doc = patched(r#"{"a": {"b": 1}, "c": []}"#, r#"[{"op": "move", "from": "/a/b", "path": "/c/0"}]"#).unwrap();
+        assert_eq!(doc, parse(r#"{"a": {}, "c": [1]}"#).unwrap());
+
+        let err = patched(r#"{"a": {"b": 1}}"#, r#"[{"op": "move", "from": "/a", "path": "/a/b/c"}]"#).unwrap_err();
+        assert_eq!(err.message(), "Cannot move /a into its own child /a/b/c");
+    }
+
+    #[test]
+    fn test_copy() {
+        let doc = patched(r#"{"a": {"b": [1]}}"#, r#"[{"op": "copy", "from": "/a/b", "path": "/c"}]"#).unwrap();
+        assert_eq!(doc, parse(r#"{"a": {"b": [1]}, "c": [1]}"#).unwrap());
+    }
+
+    #[test]
+    fn test_test_op() {
+        assert!(patched(r#"{"a": "x"}"#, r#"[{"op": "test", "path": "/a", "value": "x"}]"#).is_ok());
+
+        // The failed test aborts the patch, so the earlier add is rolled back
+        let mut doc = parse(r#"{"a": "x"}"#).unwrap();
+        let patch = parse(r#"[
+            {"op": "add", "path": "/b", "value": 1},
+            {"op": "test", "path": "/a", "value": "y"}
+        ]"#).unwrap();
+        let err = apply_patch(&mut doc, &patch).unwrap_err();
+        assert_eq!(err, ParseError::Validation("Test failed at /a".to_string()));
+        assert_eq!(doc, parse(r#"{"a": "x"}"#).unwrap());
+    }
+
+    #[test]
+    fn test_malformed_operations() {
+        assert_eq!(patched("{}", "{}").unwrap_err().message(), "Patch must be an array of operations");
+        assert_eq!(patched("{}", r#"[{"path": "/a"}]"#).unwrap_err().message(), "Patch operation is missing `op`");
+        assert_eq!(patched("{}", r#"[{"op": "add", "path": "/a"}]"#).unwrap_err().message(), "Patch operation is missing `value`");
+        assert_eq!(patched("{}", r#"[{"op": "frob", "path": "/a"}]"#).unwrap_err().message(), "Unknown patch op `frob`");
+    }
+}
@@ -0,0 +1,151 @@
+//! JSON Pointer (RFC 6901) lookups.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::value::JsonValue;
+
+// Splits a pointer into unescaped reference tokens. Returns None if the
+// pointer is non-empty but doesn't start with '/'.
+pub(crate) fn segments(ptr: &str) -> Option<Vec<String>> {
+    if ptr.is_empty() {
+        return Some(Vec::new());
+    }
+    let rest = ptr.strip_prefix('/')?;
+    Some(rest.split('/').map(|s| s.replace("~1", "/").replace("~0", "~")).collect())
+}
+
+// Escapes a key for use as one pointer segment ('~' first, then '/').
+pub(crate) fn escape_segment(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+// The pointer of the value a recursive walk is visiting. `with_index` and
+// `with_key` descend one level for the duration of a closure, so the path
+// is always restored on the way back up.
+pub(crate) struct PointerPath(String);
+
+impl PointerPath {
+    pub(crate) fn new() -> Self {
+        PointerPath(String::new())
+    }
+
+    pub(crate) fn to_pointer(&self) -> String {
+        self.0.clone()
+    }
+
+    pub(crate) fn with_index<T>(&mut self, index: usize, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.with_segment(&index.to_string(), f)
+    }
+
+    pub(crate) fn with_key<T>(&mut self, key: &str, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.with_segment(&escape_segment(key), f)
+    }
+
+    fn with_segment<T>(&mut self, segment: &str, f: impl FnOnce(&mut Self) -> T) -> T {
+        let len = self.0.len();
+        self.0.push('/');
+        self.0.push_str(segment);
+        let result = f(self);
+        self.0.truncate(len);
+        result
+    }
+}
+
+// Array indices are plain decimal digits without leading zeros.
+pub(crate) fn array_index(segment: &str) -> Option<usize> {
+    if segment.is_empty() || !segment.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    if segment.len() > 1 && segment.starts_with('0') {
+        return None;
+    }
+    segment.parse().ok()
+}
+
+/// Resolves `ptr` against `value`.
+pub fn pointer<'a>(value: &'a JsonValue, ptr: &str) -> Option<&'a JsonValue> {
+    let mut current = value;
+    for segment in segments(ptr)? {
+        current = match current {
+            JsonValue::Object(_) => current.get(&segment)?,
+            JsonValue::Array(elements) => elements.get(array_index(&segment)?)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+/// Resolves `ptr` against `value` for in-place modification.
+///
+/// Returns `None` if any segment is missing or walks into a scalar.
+pub fn pointer_mut<'a>(value: &'a mut JsonValue, ptr: &str) -> Option<&'a mut JsonValue> {
+    resolve_mut(value, &segments(ptr)?)
+}
+
+// pointer_mut over already split segments
+pub(crate) fn resolve_mut<'a>(value: &'a mut JsonValue, segments: &[String]) -> Option<&'a mut JsonValue> {
+    let mut current = value;
+    for segment in segments {
+        current = match current {
+            JsonValue::Object(entries) => entries.iter_mut().rev().find(|(k, _)| k == segment).map(|(_, v)| v)?,
+            JsonValue::Array(elements) => elements.get_mut(array_index(segment)?)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+/// Replaces the value at `ptr` with `new`, returning the value it replaced.
+///
+/// Returns `None` (and drops `new`) if `ptr` doesn't resolve.
+pub fn set(value: &mut JsonValue, ptr: &str, new: JsonValue) -> Option<JsonValue> {
+    pointer_mut(value, ptr).map(|slot| core::mem::replace(slot, new))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_pointer_path() {
+        let mut path = PointerPath::new();
+        let inner = path.with_key("a/b", |p| p.with_index(3, |p| p.with_key("~", |p| p.to_pointer())));
+        assert_eq!(inner, "/a~1b/3/~0");
+        assert_eq!(path.to_pointer(), "");
+    }
+
+    #[test]
+    fn test_pointer_lookup() {
+        let doc = parse(r#"{"users": [{"name": "ana"}], "a/b": 1, "m~n": 2}"#).unwrap();
+        assert_eq!(pointer(&doc, ""), Some(&doc));
+        assert_eq!(pointer(&doc, "/users/0/name"), Some(&JsonValue::String("ana".to_string())));
+        assert_eq!(pointer(&doc, "/a~1b"), Some(&JsonValue::Number(1.0)));
+        assert_eq!(pointer(&doc, "/m~0n"), Some(&JsonValue::Number(2.0)));
+        assert_eq!(pointer(&doc, "/users/01"), None);
+        assert_eq!(pointer(&doc, "users"), None);
+    }
+
+    #[test]
+    fn test_pointer_mut_nested_scalar() {
+        let mut doc = parse(r#"{"server": {"ports": [80, 443]}}"#).unwrap();
+        *pointer_mut(&mut doc, "/server/ports/1").unwrap() = JsonValue::Number(8443.0);
+        assert_eq!(doc, parse(r#"{"server": {"ports": [80, 8443]}}"#).unwrap());
+
+        let old = set(&mut doc, "/server/ports/0", JsonValue::Number(8080.0));
+        assert_eq!(old, Some(JsonValue::Number(80.0)));
+        assert_eq!(doc, parse(r#"{"server": {"ports": [8080, 8443]}}"#).unwrap());
+    }
+
+    #[test]
+    fn test_pointer_mut_missing_path() {
+        let mut doc = parse(r#"{"server": {"ports": [80]}}"#).unwrap();
+        assert!(pointer_mut(&mut doc, "/server/host").is_none());
+        assert!(pointer_mut(&mut doc, "/server/ports/1").is_none());
+        // Walking through a scalar is a type mismatch
+        assert!(pointer_mut(&mut doc, "/server/ports/0/x").is_none());
+        assert_eq!(set(&mut doc, "/missing", JsonValue::Null), None);
+        assert_eq!(doc, parse(r#"{"server": {"ports": [80]}}"#).unwrap());
+    }
+}
@@ -0,0 +1,241 @@
+//! Validation that keeps going after an error, so a badly broken document
+//! can be fixed in one pass instead of one error at a time.
+
+use alloc::format;
+use alloc::vec::Vec;
+
+use crate::error::ParseError;
+use crate::lexer::{Lexer, Position, Token};
+use crate::options::{CommaPolicy, ParseOptions};
+use crate::parse_with_options;
+
+/// Parses `input`, or collects every error found in it.
+///
+/// After an error the checker resynchronizes and carries on: something
+/// that doesn't lex stands in for a value, a missing comma or colon is
+/// assumed, and a stray bracket closes the open container. One mistake can
+/// still cause a follow-up error or two. At most `max_errors` errors are
+/// kept; if there were more, a final `ParseError::Validation` says "... and
+/// N more errors suppressed".
+///
+/// Grammar options that the checker doesn't know about, such as
+/// `forbid_empty_containers`, are only caught by the final parse and give
+/// a single error.
+pub fn parse_collecting(input: &str, options: &ParseOptions, max_errors: usize) -> Result<crate::JsonValue, Vec<ParseError>> {
+    let mut errors = Errors { kept: Vec::new(), suppressed: 0, max: max_errors };
+
+    let mut lexer = Lexer::with_options(input, options.clone());
+    let mut checker = Checker { stack: Vec::new(), expect: Expect::Value, commas: options.commas };
+    loop {
+        match lexer.next_token() {
+            Ok(Some((token, position))) => checker.token(&token, position, &mut errors),
+            Ok(None) => break,
+            Err(e) => {
+                let position = e.position().unwrap_or_else(|| lexer.location());
+                errors.push(e);
+                if lexer.location().offset == position.offset {
+                    lexer.skip_char();
+                }
+                checker.token(&Token::Null, position, &mut errors);
+            }
+        }
+        if checker.expect == Expect::Stopped {
+            break;
+        }
+    }
+    checker.finish(lexer.location(), &mut errors);
+
+    if errors.kept.is_empty() && errors.suppressed == 0 {
+        return parse_with_options(input, options).map_err(|e| alloc::vec![e]);
+    }
+    if errors.suppressed > 0 {
+        errors.kept.push(ParseError::Validation(format!("... and {} more errors suppressed", errors.suppressed)));
+    }
+    Err(errors.kept)
+}
+
+struct Errors {
+    kept: Vec<ParseError>,
+    suppressed: usize,
+    max: usize,
+}
+
+impl Errors {
+    fn push(&mut self, error: ParseError) {
+        if self.kept.len() < self.max {
+            self.kept.push(error);
+        } else {
+            self.suppressed += 1;
+        }
+    }
+
+    fn at(&mut self, message: &str, position: Position) {
+        self.push(ParseError::at(message, position));
+    }
+}
+
+// What the checker is waiting for
+#[derive(Clone, Copy, PartialEq)]
+enum Expect {
+    // A value right after '['
+    FirstValue,
+    Value,
+    // A key right after '{'
+    FirstKey,
+    Key,
+    Colon,
+    CommaOrClose,
+    // The top-level value is complete
+    Done,
+    // Something followed the top-level value; nothing more is checked
+    Stopped,
+}
+
+// Follows the token stream like the parser does, recording errors instead
+// of stopping at the first one
+struct Checker {
+    // `true` for an object, `false` for an array
+    stack: Vec<bool>,
+    expect: Expect,
+    commas: CommaPolicy,
+}
+
+impl Checker {
+    fn token(&mut self, token: &Token, position: Position, errors: &mut Errors) {
+        // Looping again looks at the same token in the new state
+        while !self.step(token, position, errors) {}
+    }
+
+    // Returns whether `token` was used up
+    fn step(&mut self, token: &Token, position: Position, errors: &mut Errors) -> bool {
+        let in_object = self.stack.last().copied();
+        let closes_top = matches!((token, in_object), (Token::RightBrace, Some(true)) | (Token::RightBracket, Some(false)));
+
+        match self.expect {
+            Expect::Stopped => {}
+            Expect::Done => {
+                errors.at("Unexpected token after JSON value", position);
+                self.expect = Expect::Stopped;
+            }
+            Expect::Value | Expect::FirstValue => match token {
+                Token::LeftBrace => {
+                    self.stack.push(true);
+                    self.expect = Expect::FirstKey;
+                }
+                Token::LeftBracket => {
+                    self.stack.push(false);
+                    self.expect = Expect::FirstValue;
+                }
+                Token::String(_) | Token::Number(_) | Token::Boolean(_) | Token::Null => self.end_value(),
+                _ if closes_top => {
+                    if self.expect == Expect::Value {
+                        errors.at(if in_object == Some(false) { "Trailing comma not allowed" } else { "Expected value" }, position);
+                    }
+                    self.close();
+                }
+                _ => errors.at("Expected value", position),
+            },
+            Expect::Key | Expect::FirstKey => match token {
+                Token::String(_) => self.expect = Expect::Colon,
+                _ if closes_top => {
+                    if self.expect == Expect::Key {
+                        errors.at("Trailing comma not allowed", position);
+                    }
+                    self.close();
+                }
+                _ => errors.at("Expected string key", position),
+            },
+            Expect::Colon => {
+                self.expect = Expect::Value;
+                if *token != Token::Colon {
+                    // Carry on as if the colon were there
+                    errors.at("Expected ':'", position);
+                    return false;
+                }
+            }
+            Expect::CommaOrClose => match token {
+                Token::Comma => self.expect = if in_object == Some(true) { Expect::Key } else { Expect::Value },
+                _ if closes_top => self.close(),
+                _ => {
+                    let object = in_object == Some(true);
+                    if self.commas == CommaPolicy::AllowMissing && !matches!(token, Token::RightBrace | Token::RightBracket) {
+                        self.expect = if object { Expect::Key } else { Expect::Value };
+                        return false;
+                    }
+                    errors.at(if object { "Expected ',' or '}'" } else { "Expected ',' or ']'" }, position);
+                    if matches!(token, Token::RightBrace | Token::RightBracket) {
+                        // A mismatched bracket still ends the container
+                        self.close();
+                    } else {
+                        // Carry on as if a comma were missing
+                        self.expect = if object { Expect::Key } else { Expect::Value };
+                        return false;
+                    }
+                }
+            },
+        }
+        true
+    }
+
+    fn close(&mut self) {
+        self.stack.pop();
+        self.end_value();
+    }
+
+    fn end_value(&mut self) {
+        self.expect = if self.stack.is_empty() { Expect::Done } else { Expect::CommaOrClose };
+    }
+
+    fn finish(&self, end: Position, errors: &mut Errors) {
+        match self.expect {
+            Expect::Done | Expect::Stopped => {}
+            Expect::Value | Expect::FirstValue => errors.at("Expected value", end),
+            _ => errors.at("Unexpected end of input", end),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn messages(input: &str, max_errors: usize) -> Vec<alloc::string::String> {
+        let errors = parse_collecting(input, &ParseOptions::default(), max_errors).unwrap_err();
+        errors.iter().map(|e| e.message().into()).collect()
+    }
+
+    #[test]
+    fn test_valid_input_parses() {
+        let input = r#"{"a": [1, 2, {"b": null}], "c": "d"}"#;
+        assert_eq!(parse_collecting(input, &ParseOptions::default(), 10).unwrap(), parse(input).unwrap());
+    }
+
+    #[test]
+    fn test_collects_several_errors() {
+        let input = "{\n  \"a\": 1\n  \"b\": @,\n  \"c\" 3,\n  \"d\": [1, 2,]\n}";
+        let errors = parse_collecting(input, &ParseOptions::default(), 10).unwrap_err();
+        let found: Vec<(&str, usize)> = errors.iter().map(|e| (e.message(), e.position().unwrap().line)).collect();
+        assert_eq!(found, vec![
+            ("Expected ',' or '}'", 3),
+            ("Invalid character in JSON", 3),
+            ("Expected ':'", 4),
+            ("Trailing comma not allowed", 5),
+        ]);
+    }
+
+    #[test]
+    fn test_max_errors_cap() {
+        let input = format!("[{}]", alloc::vec!["@"; 50].join(", "));
+        let found = messages(&input, 5);
+        assert_eq!(found.len(), 6);
+        assert!(found[..5].iter().all(|m| m == "Invalid character in JSON"));
+        assert_eq!(found[5], "... and 45 more errors suppressed");
+
+        assert_eq!(messages("[1, 2", 5), vec!["Unexpected end of input"]);
+        assert_eq!(messages("[1}", 5), vec!["Expected ',' or ']'"]);
+        assert_eq!(messages("[1] 2 3", 5), vec!["Unexpected token after JSON value"]);
+        assert_eq!(messages("[undefined, 1, NaN]", 5), vec!["Invalid identifier", "Invalid identifier"]);
+        assert_eq!(messages("", 0), vec!["... and 1 more errors suppressed"]);
+    }
+}
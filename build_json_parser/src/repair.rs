@@ -0,0 +1,193 @@
+//! Best-effort fixes for hand-edited JSON that almost parses.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::iter::Peekable;
+use core::str::Chars;
+
+use crate::error::ParseError;
+use crate::lexer::Position;
+use crate::parse;
+
+/// Rewrites `input` into valid JSON, fixing the usual hand-editing
+/// mistakes: trailing commas are dropped, bare object keys and
+/// single-quoted strings get double quotes, and containers still open at
+/// the end of the input are closed. Whitespace is not kept.
+///
+/// Anything else is left alone, so the result is parsed once more before
+/// it is returned. Input that can't be fixed without guessing, such as a
+/// bracket closing the wrong kind of container, a bare word where a value
+/// belongs or a string that never ends, gives an error instead.
+pub fn repair(input: &str) -> Result<String, ParseError> {
+    let mut scanner = Scanner { chars: input.chars().peekable(), position: Position { line: 1, column: 1, offset: 0 } };
+    let mut out = String::new();
+    let mut open = Vec::new();
+    // Whether `out` ends with a value, so two values in a row stay apart
+    let mut after_value = false;
+
+    while let Some((c, at)) = scanner.next_significant() {
+        let value_starts = !matches!(c, '}' | ']' | ':' | ',');
+        if value_starts && after_value {
+            out.push(' ');
+        }
+        match c {
+            '{' | '[' => open.push(c),
+            '}' | ']' => {
+                let opener = if c == '}' { '{' } else { '[' };
+                if open.pop() != Some(opener) {
+                    return Err(ParseError::at("Closing bracket doesn't match the open container", at));
+                }
+                if out.ends_with(',') {
+                    out.pop();
+                }
+            }
+            _ => {}
+        }
+        match c {
+            '{' | '[' | '}' | ']' | ':' | ',' => out.push(c),
+            '"' | '\'' => out.push_str(&scanner.string(c, at)?),
+            '-' | '0'..='9' => {
+                out.push(c);
+                while let Some(&next) = scanner.chars.peek() {
+                    if !matches!(next, '0'..='9' | '+' | '-' | '.' | 'e' | 'E') {
+                        break;
+                    }
+                    out.push(scanner.bump());
+                }
+            }
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let mut word = String::from(c);
+                while let Some(&next) = scanner.chars.peek() {
+                    if !(next.is_alphanumeric() || next == '_' || next == '$') {
+                        break;
+                    }
+                    word.push(scanner.bump());
+                }
+                if open.last() == Some(&'{') && scanner.peek_significant() == Some(':') {
+                    out.push_str(&format!("\"{}\"", word));
+                } else if matches!(word.as_str(), "true" | "false" | "null") {
+                    out.push_str(&word);
+                } else {
+                    return Err(ParseError::at("Bare word where a value belongs", at));
+                }
+            }
+            _ => return Err(ParseError::at("Unexpected character", at)),
+        }
+        after_value = !matches!(c, '{' | '[' | ':' | ',');
+    }
+
+    if out.ends_with(',') {
+        out.pop();
+    }
+    for opener in open.into_iter().rev() {
+        out.push(if opener == '{' { '}' } else { ']' });
+    }
+
+    match parse(&out) {
+        Ok(_) => Ok(out),
+        Err(e) => Err(ParseError::Validation(format!("Still invalid after repair: {}", e.message()))),
+    }
+}
+
+struct Scanner<'a> {
+    chars: Peekable<Chars<'a>>,
+    position: Position,
+}
+
+impl Scanner<'_> {
+    // Only called after a successful peek
+    fn bump(&mut self) -> char {
+        let c = self.chars.next().unwrap();
+        self.position.offset += c.len_utf8();
+        if c == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.bump();
+        }
+    }
+
+    fn next_significant(&mut self) -> Option<(char, Position)> {
+        self.skip_whitespace();
+        let at = self.position;
+        self.chars.peek()?;
+        Some((self.bump(), at))
+    }
+
+    fn peek_significant(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.peek().copied()
+    }
+
+    // Reads the rest of a string opened by `quote` and returns it
+    // double-quoted. Escapes are copied as they are, except `\'`, which
+    // JSON doesn't have.
+    fn string(&mut self, quote: char, at: Position) -> Result<String, ParseError> {
+        let mut out = String::from('"');
+        loop {
+            let c = match self.chars.peek() {
+                Some(_) => self.bump(),
+                None => return Err(ParseError::at("Unterminated string", at)),
+            };
+            match c {
+                '\\' => {
+                    let escaped = match self.chars.peek() {
+                        Some(_) => self.bump(),
+                        None => return Err(ParseError::at("Unterminated string", at)),
+                    };
+                    if escaped != '\'' {
+                        out.push('\\');
+                    }
+                    out.push(escaped);
+                }
+                '"' if quote == '\'' => out.push_str("\\\""),
+                c if c == quote => break,
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repair_trailing_commas() {
+        assert_eq!(repair(r#"{"a": 1, "b": [1, 2, ], }"#).unwrap(), r#"{"a":1,"b":[1,2]}"#);
+        assert_eq!(repair("[\n  true,\n  null,\n]").unwrap(), "[true,null]");
+    }
+
+    #[test]
+    fn test_repair_single_quotes() {
+        assert_eq!(repair(r#"{'name': 'it\'s "fine"', 'n': 'a\nb'}"#).unwrap(), r#"{"name":"it's \"fine\"","n":"a\nb"}"#);
+        assert_eq!(parse(&repair("['x', \"y\"]").unwrap()).unwrap(), parse(r#"["x", "y"]"#).unwrap());
+    }
+
+    #[test]
+    fn test_repair_bare_keys_and_missing_closers() {
+        assert_eq!(repair("{name: 'ana', tags: ['a', 'b'], $ref: null").unwrap(), r#"{"name":"ana","tags":["a","b"],"$ref":null}"#);
+        assert_eq!(repair(r#"[1, {"a": [2,"#).unwrap(), r#"[1,{"a":[2]}]"#);
+        assert_eq!(repair(r#"{"ok": true}"#).unwrap(), r#"{"ok":true}"#);
+    }
+
+    #[test]
+    fn test_repair_declines_ambiguous_input() {
+        let err = repair(r#"{"a": [1, 2}"#).unwrap_err();
+        assert_eq!(err, ParseError::at("Closing bracket doesn't match the open container", Position { line: 1, column: 12, offset: 11 }));
+        assert_eq!(repair(r#"{"a": yes}"#).unwrap_err().message(), "Bare word where a value belongs");
+        assert_eq!(repair(r#"["open"#).unwrap_err().message(), "Unterminated string");
+        assert_eq!(repair(r#"{"a":"#).unwrap_err().message(), "Still invalid after repair: Expected value");
+        assert_eq!(repair("[1 2]").unwrap_err().message(), "Still invalid after repair: Expected ',' or ']'");
+    }
+}
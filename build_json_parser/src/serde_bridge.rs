@@ -0,0 +1,79 @@
+//! Conversions between `JsonValue` and `serde_json::Value`.
+//!
+//! `serde_json::Map` cannot hold the same key twice, so when an object with
+//! duplicate keys is converted the last occurrence wins, matching how
+//! `serde_json` itself parses such documents. Key order then follows
+//! `serde_json`'s map, which is sorted unless its `preserve_order` feature is
+//! enabled.
+
+use serde_json::{Map, Number, Value};
+
+use crate::value::JsonValue;
+
+impl From<JsonValue> for Value {
+    fn from(value: JsonValue) -> Self {
+        match value {
+            JsonValue::Null => Value::Null,
+            JsonValue::Boolean(b) => Value::Bool(b),
+            // Whole numbers become integers so `1` doesn't come back as `1.0`;
+            // -0.0 stays a float to keep its sign. Non-finite numbers have no
+            // JSON form and become null.
+            JsonValue::Number(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 && !(n == 0.0 && n.is_sign_negative()) => {
+                Value::Number(Number::from(n as i64))
+            }
+            JsonValue::Number(n) => Number::from_f64(n).map_or(Value::Null, Value::Number),
+            JsonValue::String(s) => Value::String(s),
+            JsonValue::Array(elements) => Value::Array(elements.into_iter().map(Value::from).collect()),
+            JsonValue::Object(entries) => {
+                let mut map = Map::new();
+                for (key, value) in entries {
+                    map.insert(key, Value::from(value));
+                }
+                Value::Object(map)
+            }
+        }
+    }
+}
+
+impl From<Value> for JsonValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => JsonValue::Null,
+            Value::Bool(b) => JsonValue::Boolean(b),
+            // Integers beyond 2^53 lose precision here, as they would when lexed.
+            Value::Number(n) => JsonValue::Number(n.as_f64().unwrap_or(f64::NAN)),
+            Value::String(s) => JsonValue::String(s),
+            Value::Array(elements) => JsonValue::Array(elements.into_iter().map(JsonValue::from).collect()),
+            Value::Object(map) => JsonValue::Object(map.into_iter().map(|(k, v)| (k, JsonValue::from(v))).collect()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_round_trip_through_serde_json() {
+        let original = parse(r#"{"a": [1, 2.5, -3], "b": {"c": null, "d": true}, "e": "text"}"#).unwrap();
+        let bridged = Value::from(original.clone());
+        assert_eq!(bridged, serde_json::json!({"a": [1, 2.5, -3], "b": {"c": null, "d": true}, "e": "text"}));
+        assert_eq!(JsonValue::from(bridged), original);
+
+        // `-0.0 == 0.0`, so the sign has to be checked on its own
+        let bridged = Value::from(parse("-0.0").unwrap());
+        assert_eq!(bridged.to_string(), "-0.0");
+        match JsonValue::from(bridged) {
+            JsonValue::Number(n) => assert!(n == 0.0 && n.is_sign_negative()),
+            other => panic!("expected a number, got {:?}", other),
+        }
+        assert_eq!(Value::from(parse("0").unwrap()).to_string(), "0");
+    }
+
+    #[test]
+    fn test_duplicate_keys_keep_last() {
+        let value = parse(r#"{"a": 1, "a": 2}"#).unwrap();
+        assert_eq!(Value::from(value), serde_json::json!({"a": 2}));
+    }
+}
@@ -0,0 +1,304 @@
+//! Turning a `JsonValue` back into JSON text.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+use crate::options::FormatOptions;
+use crate::value::JsonValue;
+
+/// Serializes `value` without any insignificant whitespace.
+pub fn to_string(value: &JsonValue) -> String {
+    let mut out = String::new();
+    write_compact(value, &mut out);
+    out
+}
+
+/// Serializes `value` with two-space indentation, one member or element per
+/// line. Empty containers stay on one line as `{}` and `[]`.
+pub fn to_string_pretty(value: &JsonValue) -> String {
+    let mut out = String::new();
+    write_pretty(value, 0, &mut out);
+    out
+}
+
+/// Serializes `value` compactly or indented, optionally ending with a
+/// newline, as chosen by `options`.
+pub fn to_string_with(value: &JsonValue, options: &FormatOptions) -> String {
+    let mut out = String::new();
+    if options.pretty {
+        write_pretty(value, 0, &mut out);
+    } else {
+        write_compact(value, &mut out);
+    }
+    if options.final_newline {
+        out.push('\n');
+    }
+    out
+}
+
+/// Length in bytes of `to_string(value)`, computed without building the
+/// string.
+pub fn serialized_len(value: &JsonValue) -> usize {
+    match value {
+        JsonValue::Null => 4,
+        JsonValue::Boolean(b) => if *b { 4 } else { 5 },
+        JsonValue::Number(n) if n.is_finite() => {
+            let mut counter = LenCounter(0);
+            let _ = write!(counter, "{}", n);
+            counter.0
+        }
+        JsonValue::Number(_) => 4,
+        JsonValue::String(s) => string_len(s),
+        JsonValue::Array(elements) => {
+            2 + elements.len().saturating_sub(1) + elements.iter().map(serialized_len).sum::<usize>()
+        }
+        JsonValue::Object(entries) => {
+            // Each entry adds a ':' and all but the last a ','
+            2 + (entries.len() * 2).saturating_sub(1)
+                + entries.iter().map(|(k, v)| string_len(k) + serialized_len(v)).sum::<usize>()
+        }
+    }
+}
+
+// A fmt::Write sink that only counts bytes
+struct LenCounter(usize);
+
+impl Write for LenCounter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+// Quoted and escaped length of `s`, mirroring write_string
+fn string_len(s: &str) -> usize {
+    2 + s.chars()
+        .map(|c| match c {
+            '"' | '\\' | '\n' | '\r' | '\t' | '\u{08}' | '\u{0c}' => 2,
+            c if (c as u32) < 0x20 => 6,
+            c => c.len_utf8(),
+        })
+        .sum::<usize>()
+}
+
+/// Returns a copy of `value` with every object's keys sorted, recursively.
+/// Arrays keep their order, and duplicate keys keep their relative order.
+pub fn sort_keys(value: &JsonValue) -> JsonValue {
+    match value {
+        JsonValue::Array(elements) => JsonValue::Array(elements.iter().map(sort_keys).collect()),
+        JsonValue::Object(entries) => {
+            let mut sorted: Vec<(String, JsonValue)> = entries.iter().map(|(k, v)| (k.clone(), sort_keys(v))).collect();
+            sorted.sort_by(|(a, _), (b, _)| a.cmp(b));
+            JsonValue::Object(sorted)
+        }
+        scalar => scalar.clone(),
+    }
+}
+
+/// A stable 64-bit FNV-1a hash of the compact, key-sorted serialization.
+///
+/// Documents that differ only in whitespace or key order hash the same,
+/// and the value doesn't change between runs or platforms, so it can be
+/// used as a cache key.
+pub fn canonical_hash(value: &JsonValue) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    to_string(&sort_keys(value))
+        .bytes()
+        .fold(OFFSET_BASIS, |hash, byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+}
+
+// Non-finite numbers have no JSON spelling, so they're written as null.
+pub(crate) fn format_number(n: f64) -> String {
+    if n.is_finite() {
+        n.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+pub(crate) fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0c}' => out.push_str("\\f"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn write_scalar(value: &JsonValue, out: &mut String) {
+    match value {
+        JsonValue::Null => out.push_str("null"),
+        JsonValue::Boolean(b) => out.push_str(if *b { "true" } else { "false" }),
+        JsonValue::Number(n) => out.push_str(&format_number(*n)),
+        JsonValue::String(s) => write_string(s, out),
+        JsonValue::Array(_) | JsonValue::Object(_) => unreachable!("containers are written by the caller"),
+    }
+}
+
+fn write_compact(value: &JsonValue, out: &mut String) {
+    match value {
+        JsonValue::Array(elements) => {
+            out.push('[');
+            for (i, element) in elements.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_compact(element, out);
+            }
+            out.push(']');
+        }
+        JsonValue::Object(entries) => {
+            out.push('{');
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(key, out);
+                out.push(':');
+                write_compact(value, out);
+            }
+            out.push('}');
+        }
+        scalar => write_scalar(scalar, out),
+    }
+}
+
+fn write_indent(depth: usize, out: &mut String) {
+    for _ in 0..depth {
+        out.push_str("  ");
+    }
+}
+
+fn write_pretty(value: &JsonValue, depth: usize, out: &mut String) {
+    match value {
+        JsonValue::Array(elements) if elements.is_empty() => out.push_str("[]"),
+        JsonValue::Object(entries) if entries.is_empty() => out.push_str("{}"),
+        JsonValue::Array(elements) => {
+            out.push_str("[\n");
+            for (i, element) in elements.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(",\n");
+                }
+                write_indent(depth + 1, out);
+                write_pretty(element, depth + 1, out);
+            }
+            out.push('\n');
+            write_indent(depth, out);
+            out.push(']');
+        }
+        JsonValue::Object(entries) => {
+            out.push_str("{\n");
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(",\n");
+                }
+                write_indent(depth + 1, out);
+                write_string(key, out);
+                out.push_str(": ");
+                write_pretty(value, depth + 1, out);
+            }
+            out.push('\n');
+            write_indent(depth, out);
+            out.push('}');
+        }
+        scalar => write_scalar(scalar, out),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_compact() {
+        let value = parse(r#"{ "a" : [1, 2.5, -3e2], "b": {"c": null, "d": [true, false]}, "e": "x" }"#).unwrap();
+        assert_eq!(to_string(&value), r#"{"a":[1,2.5,-300],"b":{"c":null,"d":[true,false]},"e":"x"}"#);
+        assert_eq!(parse(&to_string(&value)).unwrap(), value);
+    }
+
+    #[test]
+    fn test_pretty() {
+        let value = parse(r#"{"a": [1, {}], "b": {"c": []}}"#).unwrap();
+        assert_eq!(to_string_pretty(&value), "{\n  \"a\": [\n    1,\n    {}\n  ],\n  \"b\": {\n    \"c\": []\n  }\n}");
+        assert_eq!(to_string_pretty(&JsonValue::Number(1.0)), "1");
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let value = JsonValue::String("say \"hi\"\\\n\t\u{1}".to_string());
+        assert_eq!(to_string(&value), r#""say \"hi\"\\\n\t\u0001""#);
+    }
+
+    #[test]
+    fn test_non_finite_numbers() {
+        assert_eq!(to_string(&JsonValue::Number(f64::INFINITY)), "null");
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let value = parse(r#"{"b": {"z": 1, "y": [{"k": 1, "j": 2}]}, "a": [3, 1, 2]}"#).unwrap();
+        assert_eq!(to_string(&sort_keys(&value)), r#"{"a":[3,1,2],"b":{"y":[{"j":2,"k":1}],"z":1}}"#);
+    }
+
+    #[test]
+    fn test_serialized_len_matches_to_string() {
+        let documents = [
+            "null",
+            "[]",
+            "{}",
+            "-0.5",
+            "[1, 2.25, -3e10, 1e400]",
+            r#"{"name": "ana", "tags": ["a", "b"], "nested": {"ok": true, "no": false}}"#,
+            "[\"caf\u{e9} \u{1F600}\", \"\u{1}\"]",
+            "{\"\": [[], {}, null]}",
+        ];
+        for text in documents {
+            let value = parse(text).unwrap();
+            assert_eq!(serialized_len(&value), to_string(&value).len(), "{}", text);
+        }
+
+        let escapes = JsonValue::String("tab\tquote\"back\\nl\n\u{7}".to_string());
+        assert_eq!(serialized_len(&escapes), to_string(&escapes).len());
+    }
+
+    #[test]
+    fn test_final_newline() {
+        let value = parse(r#"{"a": [1]}"#).unwrap();
+        let compact = FormatOptions::default();
+        let pretty = FormatOptions { pretty: true, ..FormatOptions::default() };
+        assert_eq!(to_string_with(&value, &compact), to_string(&value));
+        assert_eq!(to_string_with(&value, &pretty), to_string_pretty(&value));
+        assert!(!to_string_with(&value, &pretty).ends_with('\n'));
+
+        let compact = FormatOptions { final_newline: true, ..FormatOptions::default() };
+        let pretty = FormatOptions { pretty: true, final_newline: true };
+        assert_eq!(to_string_with(&value, &compact), "{\"a\":[1]}\n");
+        assert_eq!(to_string_with(&value, &pretty), "{\n  \"a\": [\n    1\n  ]\n}\n");
+    }
+
+    #[test]
+    fn test_canonical_hash() {
+        let a = parse(r#"{"b": [1, 2], "a": {"y": null, "x": "s"}}"#).unwrap();
+        let b = parse("{\n  \"a\": {\"x\": \"s\", \"y\": null},\n  \"b\": [1.0, 2e0]\n}").unwrap();
+        assert_eq!(canonical_hash(&a), canonical_hash(&b));
+
+        let c = parse(r#"{"b": [2, 1], "a": {"y": null, "x": "s"}}"#).unwrap();
+        assert_ne!(canonical_hash(&a), canonical_hash(&c));
+        // Fixed for a given document, so it can be stored
+        assert_eq!(canonical_hash(&JsonValue::Null), 0x5b9b_c4ba_5281_08e4);
+    }
+}
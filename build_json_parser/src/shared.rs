@@ -0,0 +1,333 @@
+//! A `JsonValue` variant whose strings are reference counted, so equal
+//! strings can share one allocation.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::rc::Rc;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::error::ParseError;
+use crate::lexer::{Position, Token};
+use crate::options::{CommaPolicy, DuplicateKeyPolicy, ParseOptions};
+use crate::stream::TokenStream;
+use crate::value::JsonValue;
+
+/// A parsed document with `Rc<str>` strings and keys.
+///
+/// Built by `parse_shared`. With `normalize_on_parse`, every occurrence of
+/// the same text points at the same allocation, and with `intern_keys` the
+/// same goes for object keys alone. That pays off for arrays of records
+/// that repeat the same keys.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SharedValue {
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(Rc<str>),
+    Array(Vec<SharedValue>),
+    Object(Vec<(Rc<str>, SharedValue)>),
+}
+
+impl SharedValue {
+    /// Copies this value into an ordinary `JsonValue`.
+    pub fn to_value(&self) -> JsonValue {
+        match self {
+            SharedValue::Null => JsonValue::Null,
+            SharedValue::Boolean(b) => JsonValue::Boolean(*b),
+            SharedValue::Number(n) => JsonValue::Number(*n),
+            SharedValue::String(s) => JsonValue::String(s.to_string()),
+            SharedValue::Array(elements) => JsonValue::Array(elements.iter().map(SharedValue::to_value).collect()),
+            SharedValue::Object(entries) => {
+                JsonValue::Object(entries.iter().map(|(k, v)| (k.to_string(), v.to_value())).collect())
+            }
+        }
+    }
+}
+
+/// Parses `input` into a `SharedValue`.
+///
+/// Keys are interned when `options.intern_keys` or
+/// `options.normalize_on_parse` is set, and string values only with
+/// `normalize_on_parse`; anything not interned gets its own `Rc`.
+///
+/// The tree is built straight from the tokens as they are lexed, so an
+/// interned string is never held twice, not even for a moment. Errors
+/// are those of `parse_with_options`, except that they are found in
+/// document order: a grammar error ahead of an invalid character is
+/// reported rather than the character.
+pub fn parse_shared(input: &str, options: &ParseOptions) -> Result<SharedValue, ParseError> {
+    let mut builder = Builder {
+        stream: TokenStream::with_options(input, options.clone()),
+        options,
+        pool: Interner {
+            pool: BTreeSet::new(),
+            keys: options.intern_keys || options.normalize_on_parse,
+            values: options.normalize_on_parse,
+        },
+    };
+    let value = builder.value()?;
+    builder.stream.finish()?;
+    Ok(value)
+}
+
+struct Interner {
+    pool: BTreeSet<Rc<str>>,
+    // Which strings go through the pool
+    keys: bool,
+    values: bool,
+}
+
+impl Interner {
+    fn string(&mut self, s: &str, intern: bool) -> Rc<str> {
+        if !intern {
+            return Rc::from(s);
+        }
+        if let Some(shared) = self.pool.get(s) {
+            return Rc::clone(shared);
+        }
+        let shared: Rc<str> = Rc::from(s);
+        self.pool.insert(Rc::clone(&shared));
+        shared
+    }
+}
+
+// Follows the grammar of `Parser`, with the same errors, but reads one
+// token at a time from the stream
+struct Builder<'a> {
+    stream: TokenStream,
+    options: &'a ParseOptions,
+    pool: Interner,
+}
+
+impl Builder<'_> {
+    // The next token, or `message` at the end of the input
+    fn expect(&mut self, message: &str) -> Result<(Token, Position), ParseError> {
+        match self.stream.next()? {
+            Some(next) => Ok(next),
+            None => Err(self.stream.error_at_end(message)),
+        }
+    }
+
+    fn value(&mut self) -> Result<SharedValue, ParseError> {
+        Ok(match self.expect("Expected value")? {
+            (Token::LeftBracket, _) => self.array()?,
+            (Token::LeftBrace, _) => self.object()?,
+            (Token::String(s), _) => SharedValue::String(self.pool.string(&s, self.pool.values)),
+            (Token::Number(n), _) => SharedValue::Number(n),
+            (Token::Boolean(b), _) => SharedValue::Boolean(b),
+            (Token::Null, _) => SharedValue::Null,
+            (_, start) => return Err(ParseError::at("Expected value", start)),
+        })
+    }
+
+    // Checks what follows the previous element or member (or the opening
+    // bracket, when `first`). Returns true once `close` has been taken.
+    fn separator(&mut self, close: &Token, first: bool) -> Result<bool, ParseError> {
+        let expected = if *close == Token::RightBrace { "Expected ',' or '}'" } else { "Expected ',' or ']'" };
+        match self.stream.peek()? {
+            None => Err(self.stream.error_at_end("Unexpected end of input")),
+            Some((token, start)) if token == close => {
+                if first && self.options.forbid_empty_containers {
+                    return Err(ParseError::at("Empty container not allowed", *start));
+                }
+                self.stream.next()?;
+                Ok(true)
+            }
+            Some((Token::Comma, _)) if !first => {
+                self.stream.next()?;
+                match self.stream.peek()? {
+                    Some((token, start)) if token == close => Err(ParseError::at("Trailing comma not allowed", *start)),
+                    _ => Ok(false),
+                }
+            }
+            Some((_, start)) if !first && self.options.commas == CommaPolicy::Required => Err(ParseError::at(expected, *start)),
+            Some(_) => Ok(false),
+        }
+    }
+
+    // After the opening bracket
+    fn array(&mut self) -> Result<SharedValue, ParseError> {
+        let mut elements = Vec::new();
+        while !self.separator(&Token::RightBracket, elements.is_empty())? {
+            elements.push(self.value()?);
+        }
+        Ok(SharedValue::Array(elements))
+    }
+
+    // After the opening brace. Repeated keys are handled as
+    // `options.duplicate_keys` says.
+    fn object(&mut self) -> Result<SharedValue, ParseError> {
+        let policy = self.options.duplicate_keys;
+        let mut entries: Vec<(Rc<str>, SharedValue)> = Vec::new();
+        // Index in `entries` of each key, unless every entry is kept
+        let mut indices: BTreeMap<Rc<str>, usize> = BTreeMap::new();
+        // Entries already turned into arrays under DuplicateKeyPolicy::Combine
+        let mut combined = BTreeSet::new();
+        let mut first = true;
+        while !self.separator(&Token::RightBrace, first)? {
+            first = false;
+            let key = match self.expect("Expected string key")? {
+                (Token::String(key), start) => {
+                    if policy == DuplicateKeyPolicy::Reject && indices.contains_key(key.as_str()) {
+                        return Err(ParseError::at("Duplicate key", start));
+                    }
+                    self.pool.string(&key, self.pool.keys)
+                }
+                (_, start) => return Err(ParseError::at("Expected string key", start)),
+            };
+            match self.expect("Expected ':'")? {
+                (Token::Colon, _) => {}
+                (_, start) => return Err(ParseError::at("Expected ':'", start)),
+            }
+            let value = self.value()?;
+
+            let existing = match policy {
+                DuplicateKeyPolicy::KeepAll => None,
+                _ => indices.get(&key).copied(),
+            };
+            match (policy, existing) {
+                (DuplicateKeyPolicy::KeepAll, _) => entries.push((key, value)),
+                (_, None) => {
+                    indices.insert(Rc::clone(&key), entries.len());
+                    entries.push((key, value));
+                }
+                (DuplicateKeyPolicy::KeepLast, Some(index)) => entries[index].1 = value,
+                (DuplicateKeyPolicy::Combine, Some(index)) => {
+                    let slot = &mut entries[index].1;
+                    if combined.insert(index) {
+                        let earlier = core::mem::replace(slot, SharedValue::Null);
+                        *slot = SharedValue::Array(vec![earlier, value]);
+                    } else if let SharedValue::Array(values) = slot {
+                        values.push(value);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(SharedValue::Object(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc_counter::peak_bytes_during;
+    use crate::{parse, parse_with_options};
+
+    fn keys(value: &SharedValue) -> Vec<Rc<str>> {
+        match value {
+            SharedValue::Array(elements) => elements.iter().flat_map(keys).collect(),
+            SharedValue::Object(entries) => entries.iter().map(|(k, _)| Rc::clone(k)).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_interned_keys_share_storage() {
+        let input = r#"[{"id": 1, "name": "id"}, {"id": 2, "name": "b"}, {"id": -0, "name": "c"}]"#;
+        let options = ParseOptions { normalize_on_parse: true, ..ParseOptions::default() };
+        let shared = parse_shared(input, &options).unwrap();
+
+        let keys = keys(&shared);
+        assert_eq!(keys.len(), 6);
+        assert!(Rc::ptr_eq(&keys[0], &keys[2]) && Rc::ptr_eq(&keys[0], &keys[4]));
+        assert!(Rc::ptr_eq(&keys[1], &keys[3]));
+        // Values share the pool with keys
+        let SharedValue::Array(records) = &shared else { panic!("expected an array") };
+        let SharedValue::Object(first) = &records[0] else { panic!("expected an object") };
+        let SharedValue::String(name) = &first[1].1 else { panic!("expected a string") };
+        assert!(Rc::ptr_eq(name, &keys[0]));
+
+        // -0 is stored as 0
+        assert_eq!(shared.to_value(), parse(r#"[{"id": 1, "name": "id"}, {"id": 2, "name": "b"}, {"id": 0, "name": "c"}]"#).unwrap());
+    }
+
+    #[test]
+    fn test_no_interning_by_default() {
+        let shared = parse_shared(r#"[{"k": 1}, {"k": 2}]"#, &ParseOptions::default()).unwrap();
+        let keys = keys(&shared);
+        assert_eq!(keys[0], keys[1]);
+        assert!(!Rc::ptr_eq(&keys[0], &keys[1]));
+    }
+
+    #[test]
+    fn test_intern_keys_only() {
+        let records: Vec<String> = (0..1000).map(|i| format!(r#"{{"id": {}, "name": "name", "active": true}}"#, i)).collect();
+        let input = format!("[{}]", records.join(", "));
+        let options = ParseOptions { intern_keys: true, ..ParseOptions::default() };
+        let shared = parse_shared(&input, &options).unwrap();
+
+        let keys = keys(&shared);
+        assert_eq!(keys.len(), 3000);
+        for (i, key) in keys.iter().enumerate() {
+            assert!(Rc::ptr_eq(key, &keys[i % 3]));
+        }
+        // One allocation, held by every record and by the clones in `keys`
+        assert_eq!(Rc::strong_count(&keys[0]), 1000 + 1000);
+
+        // String values aren't interned, even when equal to a key
+        let SharedValue::Array(records) = &shared else { panic!("expected an array") };
+        let SharedValue::Object(first) = &records[0] else { panic!("expected an object") };
+        let SharedValue::String(name) = &first[1].1 else { panic!("expected a string") };
+        assert_eq!(**name, *keys[1]);
+        assert!(!Rc::ptr_eq(name, &keys[1]));
+    }
+
+    #[test]
+    fn test_matches_parse_with_options() {
+        let input = r#"{"a": [0], "b": {"c": 1, "c": 2}, "a": 1, "a": [2], "d": -0, "e": []}"#;
+        for policy in [
+            DuplicateKeyPolicy::KeepAll,
+            DuplicateKeyPolicy::KeepFirst,
+            DuplicateKeyPolicy::KeepLast,
+            DuplicateKeyPolicy::Combine,
+            DuplicateKeyPolicy::Reject,
+        ] {
+            let options = ParseOptions { duplicate_keys: policy, intern_keys: true, ..ParseOptions::default() };
+            let shared = parse_shared(input, &options).map(|value| value.to_value());
+            assert_eq!(shared, parse_with_options(input, &options), "{:?}", policy);
+        }
+
+        let strict = ParseOptions { forbid_empty_containers: true, ..ParseOptions::default() };
+        let lenient = ParseOptions { commas: CommaPolicy::AllowMissing, ..ParseOptions::default() };
+        let cases = [
+            (ParseOptions::default(), ""),
+            (ParseOptions::default(), "[1, 2,]"),
+            (ParseOptions::default(), "[1 2]"),
+            (ParseOptions::default(), "[}"),
+            (ParseOptions::default(), r#"{"a" 1}"#),
+            (ParseOptions::default(), r#"{"a": 1,}"#),
+            (ParseOptions::default(), r#"{"a": 1 "b": 2}"#),
+            (ParseOptions::default(), r#"{1: 2}"#),
+            (ParseOptions::default(), r#"{"a": [1, {"b": "#),
+            (ParseOptions::default(), "[1] 2"),
+            (ParseOptions::default(), "[1, @]"),
+            (strict.clone(), r#"{"a": []}"#),
+            (strict, "{}"),
+            (lenient.clone(), r#"{"a": 1 "b": [1 2]}"#),
+            (lenient, "[1 2,]"),
+        ];
+        for (options, input) in cases {
+            let shared = parse_shared(input, &options).map(|value| value.to_value());
+            assert_eq!(shared, parse_with_options(input, &options), "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_interning_lowers_peak_memory() {
+        let records: Vec<String> = (0..2000)
+            .map(|i| format!(r#"{{"customer_identifier": {}, "shipping_address_line": "x", "preferred_contact_method": null}}"#, i))
+            .collect();
+        let input = format!("[{}]", records.join(", "));
+        let options = ParseOptions { intern_keys: true, ..ParseOptions::default() };
+
+        let plain = peak_bytes_during(|| {
+            parse(&input).unwrap();
+        });
+        let shared = peak_bytes_during(|| {
+            parse_shared(&input, &options).unwrap();
+        });
+        assert!(shared * 2 < plain, "parse_shared: {} bytes, parse: {} bytes", shared, plain);
+    }
+}
@@ -0,0 +1,171 @@
+//! A `JsonValue` variant that remembers where each value came from in the
+//! source text.
+
+use alloc::string::String;
+use alloc::vec::{self, Vec};
+use core::iter::Peekable;
+use core::ops::Range;
+
+use crate::error::ParseError;
+use crate::lexer::{Lexer, Token};
+use crate::options::ParseOptions;
+use crate::parse_with_options;
+use crate::value::JsonValue;
+
+/// A value together with the byte range of the source text it was parsed
+/// from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Range<usize>,
+}
+
+impl<T> Spanned<T> {
+    /// The exact source text of this value, e.g. `[1,2]` or `"a\n"` with
+    /// its quotes and escapes. `input` must be the text that was parsed.
+    pub fn source<'a>(&self, input: &'a str) -> &'a str {
+        &input[self.span.clone()]
+    }
+}
+
+/// A parsed document where every value, and every object key, carries its
+/// span. Built by `parse_with_spans`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpannedValue {
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Spanned<SpannedValue>>),
+    Object(Vec<(Spanned<String>, Spanned<SpannedValue>)>),
+}
+
+impl SpannedValue {
+    /// Copies this value into an ordinary `JsonValue`, dropping the spans.
+    pub fn to_value(&self) -> JsonValue {
+        match self {
+            SpannedValue::Null => JsonValue::Null,
+            SpannedValue::Boolean(b) => JsonValue::Boolean(*b),
+            SpannedValue::Number(n) => JsonValue::Number(*n),
+            SpannedValue::String(s) => JsonValue::String(s.clone()),
+            SpannedValue::Array(elements) => JsonValue::Array(elements.iter().map(|e| e.value.to_value()).collect()),
+            SpannedValue::Object(entries) => {
+                JsonValue::Object(entries.iter().map(|(k, v)| (k.value.clone(), v.value.to_value())).collect())
+            }
+        }
+    }
+}
+
+/// Parses `input` into a `SpannedValue`, for tools that need to map values
+/// back to the source, such as highlighting the text behind an error.
+///
+/// The document is checked with `options` first. The spanned tree then
+/// follows the source as written, so it keeps every entry of an object
+/// with repeated keys whatever `options.duplicate_keys` says.
+pub fn parse_with_spans(input: &str, options: &ParseOptions) -> Result<Spanned<SpannedValue>, ParseError> {
+    parse_with_options(input, options)?;
+
+    let mut lexer = Lexer::with_options(input, options.clone());
+    let mut tokens = Vec::new();
+    while let Some((token, start)) = lexer.next_token()? {
+        tokens.push((token, start.offset..lexer.location().offset));
+    }
+    Ok(Builder { tokens: tokens.into_iter().peekable() }.value())
+}
+
+// Rebuilds the document from tokens already known to form one
+struct Builder {
+    tokens: Peekable<vec::IntoIter<(Token, Range<usize>)>>,
+}
+
+impl Builder {
+    fn next(&mut self) -> (Token, Range<usize>) {
+        // The input parsed, so every value is complete
+        self.tokens.next().unwrap()
+    }
+
+    // Skips a separator, if there is one before the next value
+    fn skip(&mut self, separator: &Token) {
+        self.tokens.next_if(|(token, _)| token == separator);
+    }
+
+    fn value(&mut self) -> Spanned<SpannedValue> {
+        let (token, span) = self.next();
+        let value = match token {
+            Token::LeftBracket => {
+                let mut elements = Vec::new();
+                while !matches!(self.tokens.peek(), Some((Token::RightBracket, _))) {
+                    elements.push(self.value());
+                    self.skip(&Token::Comma);
+                }
+                SpannedValue::Array(elements)
+            }
+            Token::LeftBrace => {
+                let mut entries = Vec::new();
+                while !matches!(self.tokens.peek(), Some((Token::RightBrace, _))) {
+                    let key = match self.next() {
+                        (Token::String(key), span) => Spanned { value: key, span },
+                        _ => unreachable!(),
+                    };
+                    self.skip(&Token::Colon);
+                    entries.push((key, self.value()));
+                    self.skip(&Token::Comma);
+                }
+                SpannedValue::Object(entries)
+            }
+            Token::String(s) => return Spanned { value: SpannedValue::String(s), span },
+            Token::Number(n) => return Spanned { value: SpannedValue::Number(n), span },
+            Token::Boolean(b) => return Spanned { value: SpannedValue::Boolean(b), span },
+            Token::Null => return Spanned { value: SpannedValue::Null, span },
+            _ => unreachable!(),
+        };
+        // The closing bracket ends the span of a container
+        let (_, end) = self.next();
+        Spanned { value, span: span.start..end.end }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, CommaPolicy};
+
+    #[test]
+    fn test_nested_array_span() {
+        let input = r#"{"a": {"b": [1,2]}, "c": "x"}"#;
+        let root = parse_with_spans(input, &ParseOptions::default()).unwrap();
+        assert_eq!(root.span, 0..input.len());
+        assert_eq!(root.value.to_value(), parse(input).unwrap());
+
+        let SpannedValue::Object(entries) = &root.value else { panic!("expected an object") };
+        let SpannedValue::Object(inner) = &entries[0].1.value else { panic!("expected an object") };
+        let (key, array) = &inner[0];
+        assert_eq!(key.source(input), r#""b""#);
+        assert_eq!(array.source(input), "[1,2]");
+        let SpannedValue::Array(elements) = &array.value else { panic!("expected an array") };
+        assert_eq!(elements[1].source(input), "2");
+        assert_eq!(entries[0].1.source(input), r#"{"b": [1,2]}"#);
+        assert_eq!(entries[1].1.source(input), r#""x""#);
+    }
+
+    #[test]
+    fn test_spans_of_scalars_and_escapes() {
+        let input = "  [ -1.5e3 , \"caf\\u00e9\" ,true,null, [] ]  ";
+        let root = parse_with_spans(input, &ParseOptions::default()).unwrap();
+        assert_eq!(root.source(input), "[ -1.5e3 , \"caf\\u00e9\" ,true,null, [] ]");
+        let SpannedValue::Array(elements) = &root.value else { panic!("expected an array") };
+        let sources: Vec<&str> = elements.iter().map(|e| e.source(input)).collect();
+        assert_eq!(sources, ["-1.5e3", "\"caf\\u00e9\"", "true", "null", "[]"]);
+        assert_eq!(elements[1].value, SpannedValue::String("caf\u{e9}".into()));
+    }
+
+    #[test]
+    fn test_spans_follow_options() {
+        let lenient = ParseOptions { commas: CommaPolicy::AllowMissing, ..ParseOptions::default() };
+        let root = parse_with_spans("[1 [2]]", &lenient).unwrap();
+        let SpannedValue::Array(elements) = &root.value else { panic!("expected an array") };
+        assert_eq!(elements[1].span, 3..6);
+
+        assert_eq!(parse_with_spans("[1 2]", &ParseOptions::default()).unwrap_err().message(), "Expected ',' or ']'");
+    }
+}
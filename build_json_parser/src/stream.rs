@@ -0,0 +1,305 @@
+//! Element-at-a-time processing of large top-level arrays.
+
+use alloc::vec::Vec;
+
+use crate::error::ParseError;
+use crate::lexer::{Lexer, Position, Token};
+use crate::options::ParseOptions;
+use crate::parser::Parser;
+use crate::syntax_error;
+use crate::value::JsonValue;
+
+/// Parses a top-level array and hands each element to `f` as soon as it is
+/// complete.
+///
+/// Only one element's tokens and value are alive at a time, so arrays with
+/// millions of entries can be processed without materializing them all.
+/// Memory use is not independent of input size, though: the lexer keeps
+/// the whole input as `char`s, about four times its size in bytes. An
+/// error from `f` stops the walk and is returned as-is.
+pub fn for_each_array_element<F, E>(input: &str, mut f: F) -> Result<(), E>
+where
+    F: FnMut(JsonValue) -> Result<(), E>,
+    E: From<ParseError>,
+{
+    let mut stream = TokenStream::new(input);
+
+    match stream.next()? {
+        Some((Token::LeftBracket, _)) => {}
+        Some((_, start)) => return Err(ParseError::at("Expected '['", start).into()),
+        None => return Err(stream.error_at_end("Expected '['").into()),
+    }
+
+    if let Some((Token::RightBracket, _)) = stream.peek()? {
+        stream.next()?;
+        return stream.finish().map_err(E::from);
+    }
+
+    loop {
+        let element = stream.next_element()?;
+        f(element)?;
+
+        match stream.next()? {
+            Some((Token::Comma, _)) => {
+                if let Some((Token::RightBracket, start)) = stream.peek()? {
+                    return Err(ParseError::at("Trailing comma not allowed", *start).into());
+                }
+            }
+            Some((Token::RightBracket, _)) => return stream.finish().map_err(E::from),
+            Some((_, start)) => return Err(ParseError::at("Expected ',' or ']'", start).into()),
+            None => return Err(stream.error_at_end("Unexpected end of input").into()),
+        }
+    }
+}
+
+fn closer(is_object: bool) -> Token {
+    if is_object { Token::RightBrace } else { Token::RightBracket }
+}
+
+// Pulls tokens from the lexer one at a time with a single token of lookahead.
+pub(crate) struct TokenStream {
+    lexer: Lexer,
+    peeked: Option<(Token, Position)>,
+}
+
+impl TokenStream {
+    pub(crate) fn new(input: &str) -> Self {
+        TokenStream { lexer: Lexer::new(input), peeked: None }
+    }
+
+    pub(crate) fn with_options(input: &str, options: ParseOptions) -> Self {
+        TokenStream { lexer: Lexer::with_options(input, options), peeked: None }
+    }
+
+    pub(crate) fn next(&mut self) -> Result<Option<(Token, Position)>, ParseError> {
+        match self.peeked.take() {
+            Some(token) => Ok(Some(token)),
+            None => self.lexer.next_token(),
+        }
+    }
+
+    pub(crate) fn peek(&mut self) -> Result<Option<&(Token, Position)>, ParseError> {
+        if self.peeked.is_none() {
+            self.peeked = self.lexer.next_token()?;
+        }
+        Ok(self.peeked.as_ref())
+    }
+
+    pub(crate) fn error_at_end(&self, message: &str) -> ParseError {
+        ParseError::at(message, self.lexer.location())
+    }
+
+    // Byte offset just past the last token taken with next()
+    pub(crate) fn offset(&self) -> usize {
+        self.lexer.location().offset
+    }
+
+    // Collects exactly the tokens of the next value (tracking bracket
+    // depth) and parses them.
+    pub(crate) fn next_element(&mut self) -> Result<JsonValue, ParseError> {
+        let mut tokens = Vec::new();
+        let mut positions = Vec::new();
+        let mut depth = 0usize;
+
+        loop {
+            let (token, start) = match self.next()? {
+                Some(next) => next,
+                None => return Err(self.error_at_end("Unexpected end of input")),
+            };
+            match token {
+                Token::LeftBrace | Token::LeftBracket => depth += 1,
+                Token::RightBrace | Token::RightBracket if depth > 0 => depth -= 1,
+                _ => {}
+            }
+            tokens.push(token);
+            positions.push(start);
+            if depth == 0 {
+                break;
+            }
+        }
+        positions.push(self.lexer.location());
+
+        let mut parser = Parser::with_positions(tokens, positions);
+        let value = parser.parse_value().map_err(|e| syntax_error(&parser, e))?;
+        if !parser.is_at_end() {
+            return Err(syntax_error(&parser, "Expected ',' or ']'"));
+        }
+        Ok(value)
+    }
+
+    // How much of the input the lexer has read (see Lexer::progress)
+    pub(crate) fn progress(&self) -> f64 {
+        self.lexer.progress()
+    }
+
+    // Checks that the next tokens form one value, with the same errors as
+    // the parser, but one token at a time: only a stack of the containers
+    // still open is kept, so no tokens or values pile up. Calls `checked`
+    // after each value is read.
+    pub(crate) fn check_value<F: FnMut(&Self)>(&mut self, mut checked: F) -> Result<(), ParseError> {
+        // Containers still open, innermost last; true for objects
+        let mut open: Vec<bool> = Vec::new();
+        loop {
+            // A value is expected here
+            match self.next()? {
+                Some((token @ (Token::LeftBracket | Token::LeftBrace), _)) => {
+                    let is_object = token == Token::LeftBrace;
+                    match self.peek()? {
+                        // An empty container is a complete value
+                        Some((next, _)) if *next == closer(is_object) => {
+                            self.next()?;
+                        }
+                        Some(_) => {
+                            open.push(is_object);
+                            if is_object {
+                                self.check_key()?;
+                            }
+                            continue;
+                        }
+                        None => return Err(self.error_at_end("Unexpected end of input")),
+                    }
+                }
+                Some((Token::String(_) | Token::Number(_) | Token::Boolean(_) | Token::Null, _)) => {}
+                Some((_, start)) => return Err(ParseError::at("Expected value", start)),
+                None => return Err(self.error_at_end("Expected value")),
+            }
+            checked(self);
+
+            // After a value: close containers until one goes on
+            loop {
+                let Some(&is_object) = open.last() else { return Ok(()) };
+                let close = closer(is_object);
+                match self.next()? {
+                    Some((token, _)) if token == close => {
+                        open.pop();
+                        checked(self);
+                    }
+                    Some((Token::Comma, _)) => {
+                        if let Some((token, start)) = self.peek()? {
+                            if *token == close {
+                                return Err(ParseError::at("Trailing comma not allowed", *start));
+                            }
+                        }
+                        if is_object {
+                            self.check_key()?;
+                        }
+                        break;
+                    }
+                    Some((_, start)) => {
+                        let message = if is_object { "Expected ',' or '}'" } else { "Expected ',' or ']'" };
+                        return Err(ParseError::at(message, start));
+                    }
+                    None => return Err(self.error_at_end("Unexpected end of input")),
+                }
+            }
+        }
+    }
+
+    // An object key and its colon
+    fn check_key(&mut self) -> Result<(), ParseError> {
+        match self.next()? {
+            Some((Token::String(_), _)) => {}
+            Some((_, start)) => return Err(ParseError::at("Expected string key", start)),
+            None => return Err(self.error_at_end("Expected string key")),
+        }
+        match self.next()? {
+            Some((Token::Colon, _)) => Ok(()),
+            Some((_, start)) => Err(ParseError::at("Expected ':'", start)),
+            None => Err(self.error_at_end("Expected ':'")),
+        }
+    }
+
+    pub(crate) fn finish(&mut self) -> Result<(), ParseError> {
+        match self.next()? {
+            Some((_, start)) => Err(ParseError::at("Unexpected token after JSON value", start)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc_counter::live_bytes;
+
+    #[test]
+    fn test_counts_large_array() {
+        // Returns how many elements were seen, their id sum and how far
+        // live memory grew while the walk was under way
+        let walk = |count: usize| {
+            let elements: Vec<String> = (0..count).map(|i| format!(r#"{{"id": {}, "tags": ["a", "b"]}}"#, i)).collect();
+            let input = format!("[{}]", elements.join(",\n"));
+
+            let mut seen = 0;
+            let mut id_sum = 0.0;
+            let mut baseline = None;
+            let mut growth = 0;
+            let result: Result<(), ParseError> = for_each_array_element(&input, |element| {
+                seen += 1;
+                if let Some(JsonValue::Number(id)) = element.get("id") {
+                    id_sum += id;
+                }
+                let live = live_bytes();
+                growth = growth.max(live - *baseline.get_or_insert(live));
+                Ok(())
+            });
+            assert!(result.is_ok());
+            (seen, id_sum, growth)
+        };
+
+        let (seen, id_sum, small) = walk(1_000);
+        assert_eq!(seen, 1_000);
+        assert_eq!(id_sum, (0..1_000).sum::<usize>() as f64);
+
+        let (seen, id_sum, large) = walk(50_000);
+        assert_eq!(seen, 50_000);
+        assert_eq!(id_sum, (0..50_000).sum::<usize>() as f64);
+        // Keeping each element alive would add a few hundred bytes apiece
+        assert!(large < 1024 && large <= small + 64, "1000 elements: {} bytes, 50000 elements: {} bytes", small, large);
+    }
+
+    #[test]
+    fn test_empty_and_scalar_elements() {
+        let mut values = Vec::new();
+        let result: Result<(), ParseError> = for_each_array_element(r#"[1, "two", null, []]"#, |v| {
+            values.push(v);
+            Ok(())
+        });
+        assert!(result.is_ok());
+        assert_eq!(values, vec![
+            JsonValue::Number(1.0),
+            JsonValue::String("two".to_string()),
+            JsonValue::Null,
+            JsonValue::Array(vec![]),
+        ]);
+
+        let result: Result<(), ParseError> = for_each_array_element("[]", |_| panic!("no elements expected"));
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_stream_errors() {
+        let count = |input: &str| -> Result<usize, ParseError> {
+            let mut n = 0;
+            for_each_array_element(input, |_| { n += 1; Ok::<(), ParseError>(()) })?;
+            Ok(n)
+        };
+        assert_eq!(count(r#"{"a": 1}"#).unwrap_err().message(), "Expected '['");
+        assert_eq!(count("[1, 2,]").unwrap_err().message(), "Trailing comma not allowed");
+        assert_eq!(count("[1 2]").unwrap_err().message(), "Expected ',' or ']'");
+        assert_eq!(count("[1, {\"a\": }]").unwrap_err().message(), "Expected value");
+        assert_eq!(count("[1, 2").unwrap_err().message(), "Unexpected end of input");
+        assert_eq!(count("[1] 2").unwrap_err().message(), "Unexpected token after JSON value");
+    }
+
+    #[test]
+    fn test_callback_error_stops_walk() {
+        let mut seen = 0;
+        let result = for_each_array_element("[1, 2, 3]", |_| {
+            seen += 1;
+            if seen == 2 { Err(ParseError::from("stop")) } else { Ok(()) }
+        });
+        assert_eq!(result.unwrap_err().message(), "stop");
+        assert_eq!(seen, 2);
+    }
+}
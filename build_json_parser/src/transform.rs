@@ -0,0 +1,370 @@
+//! Functions that build a new `JsonValue` from an existing one, or reshape
+//! one in place.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use crate::error::ParseError;
+use crate::pointer::{array_index, segments, PointerPath};
+use crate::value::JsonValue;
+
+/// Returns a copy of `value` where every object or array nested more than
+/// `max_depth` levels deep is replaced by the string `"..."`.
+///
+/// The root container is level 1, so `max_depth = 0` replaces a container
+/// root outright. Scalars are never replaced.
+pub fn truncate_depth(value: &JsonValue, max_depth: usize) -> JsonValue {
+    match value {
+        JsonValue::Array(_) | JsonValue::Object(_) if max_depth == 0 => JsonValue::String("...".into()),
+        JsonValue::Array(elements) => {
+            JsonValue::Array(elements.iter().map(|e| truncate_depth(e, max_depth - 1)).collect())
+        }
+        JsonValue::Object(entries) => JsonValue::Object(
+            entries.iter().map(|(k, v)| (k.clone(), truncate_depth(v, max_depth - 1))).collect(),
+        ),
+        scalar => scalar.clone(),
+    }
+}
+
+/// How `merge_with` combines two arrays found at the same place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrayMerge {
+    /// The overlay's array replaces the base's.
+    Replace,
+    /// The overlay's elements are appended to the base's.
+    Concat,
+}
+
+/// Deep-merges `overlay` onto `base`, concatenating arrays.
+///
+/// See `merge_with` for the rules.
+pub fn merge(base: JsonValue, overlay: JsonValue) -> JsonValue {
+    merge_with(base, overlay, ArrayMerge::Concat)
+}
+
+/// Deep-merges `overlay` onto `base`.
+///
+/// Keys present in both objects are merged recursively, keys only in the
+/// overlay are appended, and arrays are combined according to `arrays`.
+/// Anything else (including a type mismatch) takes the overlay's value.
+/// Unlike an RFC 7386 merge patch, `null` is an ordinary value and doesn't
+/// delete keys.
+pub fn merge_with(base: JsonValue, overlay: JsonValue, arrays: ArrayMerge) -> JsonValue {
+    match (base, overlay) {
+        (JsonValue::Object(mut entries), JsonValue::Object(overlay_entries)) => {
+            for (key, value) in overlay_entries {
+                match entries.iter_mut().rev().find(|(k, _)| *k == key) {
+                    Some((_, existing)) => {
+                        let current = core::mem::replace(existing, JsonValue::Null);
+                        *existing = merge_with(current, value, arrays);
+                    }
+                    None => entries.push((key, value)),
+                }
+            }
+            JsonValue::Object(entries)
+        }
+        (JsonValue::Array(mut elements), JsonValue::Array(overlay_elements)) if arrays == ArrayMerge::Concat => {
+            elements.extend(overlay_elements);
+            JsonValue::Array(elements)
+        }
+        (_, overlay) => overlay,
+    }
+}
+
+/// How far `apply_defaults_with` looks for missing keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Defaults {
+    /// Only keys missing from the top-level object are filled in.
+    Shallow,
+    /// Objects present on both sides are filled in recursively too.
+    Deep,
+}
+
+/// Fills in keys missing from `value` with those of `defaults`, at every
+/// level. See `apply_defaults_with` for the rules.
+pub fn apply_defaults(value: &mut JsonValue, defaults: &JsonValue) {
+    apply_defaults_with(value, defaults, Defaults::Deep);
+}
+
+/// Adds each key of the `defaults` object that `value` lacks, after the
+/// existing keys and in the defaults' order. Keys already present are
+/// never overwritten, even with `null`; with `Defaults::Deep`, a present
+/// object whose default is also an object gets its own missing keys filled
+/// in. Nothing happens unless both `value` and `defaults` are objects.
+pub fn apply_defaults_with(value: &mut JsonValue, defaults: &JsonValue, depth: Defaults) {
+    let (JsonValue::Object(entries), JsonValue::Object(default_entries)) = (value, defaults) else {
+        return;
+    };
+    for (key, default) in default_entries {
+        match entries.iter_mut().find(|(k, _)| k == key) {
+            Some((_, present)) if depth == Defaults::Deep => apply_defaults_with(present, default, depth),
+            Some(_) => {}
+            None => entries.push((key.clone(), default.clone())),
+        }
+    }
+}
+
+/// Lists every scalar in `value` with its JSON Pointer, in document order,
+/// e.g. `{"a": [1]}` gives `("/a/0", 1)`. A scalar root gets the empty
+/// pointer. Empty objects and arrays are left out; see `flatten_with`.
+pub fn flatten(value: &JsonValue) -> Vec<(String, JsonValue)> {
+    flatten_with(value, false)
+}
+
+/// Like `flatten`, but with `include_empty` set, empty objects and arrays
+/// are listed too (as `{}` or `[]`) so they survive a round trip.
+pub fn flatten_with(value: &JsonValue, include_empty: bool) -> Vec<(String, JsonValue)> {
+    let mut pairs = Vec::new();
+    flatten_into(value, &mut PointerPath::new(), include_empty, &mut pairs);
+    pairs
+}
+
+fn flatten_into(value: &JsonValue, path: &mut PointerPath, include_empty: bool, pairs: &mut Vec<(String, JsonValue)>) {
+    match value {
+        JsonValue::Array(elements) if !elements.is_empty() => {
+            for (i, element) in elements.iter().enumerate() {
+                path.with_index(i, |path| flatten_into(element, path, include_empty, pairs));
+            }
+        }
+        JsonValue::Object(entries) if !entries.is_empty() => {
+            for (key, value) in entries {
+                path.with_key(key, |path| flatten_into(value, path, include_empty, pairs));
+            }
+        }
+        JsonValue::Array(_) | JsonValue::Object(_) if !include_empty => {}
+        leaf => pairs.push((path.to_pointer(), leaf.clone())),
+    }
+}
+
+/// Rebuilds a value from pointer/value pairs, the inverse of `flatten`.
+///
+/// Segments that look like array indices create arrays and anything else
+/// creates objects, so an object with keys like `"0"` comes back as an
+/// array. Indices must fill an array in order. A pointer that lands on or
+/// passes through an already placed scalar is a conflict, as is the same
+/// pointer given twice. No pairs give `null`.
+pub fn unflatten(pairs: &[(String, JsonValue)]) -> Result<JsonValue, ParseError> {
+    let mut root: Option<JsonValue> = None;
+    for (ptr, value) in pairs {
+        let segments = segments(ptr).ok_or_else(|| ParseError::Validation(format!("Invalid pointer `{}`", ptr)))?;
+        match root.as_mut() {
+            None => root = Some(build(&segments, value, ptr)?),
+            Some(_) if segments.is_empty() => return Err(conflict(ptr)),
+            Some(node) => place(node, &segments, value, ptr)?,
+        }
+    }
+    Ok(root.unwrap_or(JsonValue::Null))
+}
+
+fn conflict(ptr: &str) -> ParseError {
+    ParseError::Validation(format!("Conflicting pointer {}", ptr))
+}
+
+fn gap(ptr: &str) -> ParseError {
+    ParseError::Validation(format!("Missing array elements before {}", ptr))
+}
+
+// A fresh chain of containers ending in `value`
+fn build(segments: &[String], value: &JsonValue, ptr: &str) -> Result<JsonValue, ParseError> {
+    let Some((first, rest)) = segments.split_first() else {
+        return Ok(value.clone());
+    };
+    let child = build(rest, value, ptr)?;
+    match array_index(first) {
+        Some(0) => Ok(JsonValue::Array(vec![child])),
+        Some(_) => Err(gap(ptr)),
+        None => Ok(JsonValue::Object(vec![(first.clone(), child)])),
+    }
+}
+
+// Adds `value` under `node`, which was built by an earlier pair
+fn place(node: &mut JsonValue, segments: &[String], value: &JsonValue, ptr: &str) -> Result<(), ParseError> {
+    let (first, rest) = segments.split_first().ok_or_else(|| conflict(ptr))?;
+    match node {
+        JsonValue::Object(entries) => match entries.iter_mut().find(|(k, _)| k == first) {
+            Some((_, child)) => place(child, rest, value, ptr),
+            None => {
+                entries.push((first.clone(), build(rest, value, ptr)?));
+                Ok(())
+            }
+        },
+        JsonValue::Array(elements) => {
+            let index = array_index(first).ok_or_else(|| conflict(ptr))?;
+            if index < elements.len() {
+                place(&mut elements[index], rest, value, ptr)
+            } else if index == elements.len() {
+                elements.push(build(rest, value, ptr)?);
+                Ok(())
+            } else {
+                Err(gap(ptr))
+            }
+        }
+        _ => Err(conflict(ptr)),
+    }
+}
+
+/// Sorts an array of objects by the value each has under `key`. Numbers
+/// sort numerically and strings by bytes; across kinds, null comes first,
+/// then booleans, numbers, strings, arrays and objects. Elements without
+/// the key, including ones that aren't objects, go last.
+///
+/// The sort is stable, so ties (and arrays or objects under `key`, which
+/// aren't compared) keep their order. Anything but an array is left alone.
+pub fn sort_array_by_key(value: &mut JsonValue, key: &str) {
+    if let JsonValue::Array(elements) = value {
+        elements.sort_by(|a, b| match (a.get(key), b.get(key)) {
+            (Some(a), Some(b)) => compare_sort_keys(a, b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+    }
+}
+
+fn compare_sort_keys(a: &JsonValue, b: &JsonValue) -> Ordering {
+    let rank = |value: &JsonValue| match value {
+        JsonValue::Null => 0,
+        JsonValue::Boolean(_) => 1,
+        JsonValue::Number(_) => 2,
+        JsonValue::String(_) => 3,
+        JsonValue::Array(_) => 4,
+        JsonValue::Object(_) => 5,
+    };
+    match (a, b) {
+        (JsonValue::Boolean(a), JsonValue::Boolean(b)) => a.cmp(b),
+        // Parsed numbers are never NaN
+        (JsonValue::Number(a), JsonValue::Number(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+        (JsonValue::String(a), JsonValue::String(b)) => a.cmp(b),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, to_string};
+
+    #[test]
+    fn test_truncate_depth() {
+        let value = parse(r#"{"a": {"b": {"c": {"d": {"e": 1}}}}, "n": 1, "l": [[1], 2]}"#).unwrap();
+        let truncated = truncate_depth(&value, 2);
+        assert_eq!(to_string(&truncated), r#"{"a":{"b":"..."},"n":1,"l":["...",2]}"#);
+        // The original is untouched
+        assert_eq!(value, parse(r#"{"a": {"b": {"c": {"d": {"e": 1}}}}, "n": 1, "l": [[1], 2]}"#).unwrap());
+    }
+
+    #[test]
+    fn test_merge_recursive_objects() {
+        let base = parse(r#"{"server": {"host": "localhost", "port": 80}, "debug": false}"#).unwrap();
+        let overlay = parse(r#"{"server": {"port": 8080, "tls": true}, "name": "app"}"#).unwrap();
+        assert_eq!(
+            to_string(&merge(base, overlay)),
+            r#"{"server":{"host":"localhost","port":8080,"tls":true},"debug":false,"name":"app"}"#
+        );
+    }
+
+    #[test]
+    fn test_merge_scalar_override() {
+        let base = parse(r#"{"a": 1, "b": {"c": 2}, "d": "x"}"#).unwrap();
+        let overlay = parse(r#"{"a": "one", "b": null, "d": {"e": 3}}"#).unwrap();
+        assert_eq!(to_string(&merge(base, overlay)), r#"{"a":"one","b":null,"d":{"e":3}}"#);
+        assert_eq!(merge(JsonValue::Number(1.0), JsonValue::Boolean(true)), JsonValue::Boolean(true));
+    }
+
+    #[test]
+    fn test_merge_array_strategies() {
+        let base = parse(r#"{"tags": ["a", "b"], "nested": {"ids": [1]}}"#).unwrap();
+        let overlay = parse(r#"{"tags": ["c"], "nested": {"ids": [2, 3]}}"#).unwrap();
+
+        let concat = merge_with(base.clone(), overlay.clone(), ArrayMerge::Concat);
+        assert_eq!(to_string(&concat), r#"{"tags":["a","b","c"],"nested":{"ids":[1,2,3]}}"#);
+
+        let replace = merge_with(base, overlay, ArrayMerge::Replace);
+        assert_eq!(to_string(&replace), r#"{"tags":["c"],"nested":{"ids":[2,3]}}"#);
+    }
+
+    #[test]
+    fn test_truncate_depth_edges() {
+        let value = parse(r#"{"a": [1]}"#).unwrap();
+        assert_eq!(truncate_depth(&value, 0), JsonValue::String("...".into()));
+        assert_eq!(truncate_depth(&value, 2), value);
+        assert_eq!(truncate_depth(&JsonValue::Number(1.0), 0), JsonValue::Number(1.0));
+    }
+
+    #[test]
+    fn test_flatten() {
+        let doc = parse(r#"{"a": [1, {"b": null}], "c/d": {"e": "x"}, "f": {}, "g": []}"#).unwrap();
+        let pointers = |pairs: Vec<(String, JsonValue)>| -> Vec<String> { pairs.into_iter().map(|(p, _)| p).collect() };
+
+        let flat = flatten(&doc);
+        assert_eq!(flat, vec![
+            ("/a/0".to_string(), JsonValue::Number(1.0)),
+            ("/a/1/b".to_string(), JsonValue::Null),
+            ("/c~1d/e".to_string(), JsonValue::String("x".to_string())),
+        ]);
+        assert_eq!(pointers(flatten_with(&doc, true)), vec!["/a/0", "/a/1/b", "/c~1d/e", "/f", "/g"]);
+        assert_eq!(flatten(&JsonValue::Boolean(true)), vec![(String::new(), JsonValue::Boolean(true))]);
+    }
+
+    #[test]
+    fn test_unflatten_round_trip() {
+        let doc = parse(r#"{"a": [1, {"b": null, "c": [true, "x"]}], "d~e/f": {"g": -2.5}, "h": {}, "i": []}"#).unwrap();
+        assert_eq!(unflatten(&flatten_with(&doc, true)).unwrap(), doc);
+        assert_eq!(unflatten(&flatten(&JsonValue::Number(3.0))).unwrap(), JsonValue::Number(3.0));
+        assert_eq!(unflatten(&[]).unwrap(), JsonValue::Null);
+    }
+
+    #[test]
+    fn test_unflatten_conflicts() {
+        let pair = |ptr: &str, value: JsonValue| (ptr.to_string(), value);
+        let err = unflatten(&[pair("/a", JsonValue::Number(1.0)), pair("/a/b", JsonValue::Null)]).unwrap_err();
+        assert_eq!(err.message(), "Conflicting pointer /a/b");
+        let err = unflatten(&[pair("/a/b", JsonValue::Null), pair("/a", JsonValue::Number(1.0))]).unwrap_err();
+        assert_eq!(err.message(), "Conflicting pointer /a");
+        assert!(unflatten(&[pair("/a", JsonValue::Null), pair("/a", JsonValue::Null)]).is_err());
+        let err = unflatten(&[pair("/a/0", JsonValue::Null), pair("/a/2", JsonValue::Null)]).unwrap_err();
+        assert_eq!(err.message(), "Missing array elements before /a/2");
+        assert!(unflatten(&[pair("a", JsonValue::Null)]).is_err());
+    }
+
+    #[test]
+    fn test_sort_array_by_key() {
+        let mut doc = parse(r#"[{"n": 10, "id": "a"}, {"id": "b"}, {"n": 2, "id": "c"}, {"n": -1.5, "id": "d"}, {"n": 2, "id": "e"}]"#).unwrap();
+        sort_array_by_key(&mut doc, "n");
+        // Numbers compare numerically, ties keep their order, missing goes last
+        assert_eq!(doc, parse(r#"[{"n": -1.5, "id": "d"}, {"n": 2, "id": "c"}, {"n": 2, "id": "e"}, {"n": 10, "id": "a"}, {"id": "b"}]"#).unwrap());
+
+        let mut mixed = parse(r#"[{"n": "b"}, 7, {"n": [1]}, {"n": "a"}, {"n": true}, {"n": 3}, {"n": null}, {"n": false}]"#).unwrap();
+        sort_array_by_key(&mut mixed, "n");
+        assert_eq!(mixed, parse(r#"[{"n": null}, {"n": false}, {"n": true}, {"n": 3}, {"n": "a"}, {"n": "b"}, {"n": [1]}, 7]"#).unwrap());
+
+        let mut object = parse(r#"{"n": [2, 1]}"#).unwrap();
+        sort_array_by_key(&mut object, "n");
+        assert_eq!(object, parse(r#"{"n": [2, 1]}"#).unwrap());
+    }
+
+    #[test]
+    fn test_apply_defaults() {
+        let defaults = parse(r#"{"host": "localhost", "port": 8080, "tls": {"enabled": false, "verify": true}}"#).unwrap();
+        let mut config = parse(r#"{"port": 9000, "debug": null, "tls": {"enabled": true}}"#).unwrap();
+        apply_defaults(&mut config, &defaults);
+        // The present port is kept, and the missing host goes after the existing keys
+        assert_eq!(config, parse(r#"{"port": 9000, "debug": null, "tls": {"enabled": true, "verify": true}, "host": "localhost"}"#).unwrap());
+
+        let mut shallow = parse(r#"{"tls": {"enabled": true}}"#).unwrap();
+        apply_defaults_with(&mut shallow, &defaults, Defaults::Shallow);
+        assert_eq!(shallow, parse(r#"{"tls": {"enabled": true}, "host": "localhost", "port": 8080}"#).unwrap());
+
+        // A present key isn't replaced, whatever its type
+        let mut mismatched = parse(r#"{"tls": "off", "port": null}"#).unwrap();
+        apply_defaults(&mut mismatched, &defaults);
+        assert_eq!(mismatched, parse(r#"{"tls": "off", "port": null, "host": "localhost"}"#).unwrap());
+
+        let mut array = parse("[1]").unwrap();
+        apply_defaults(&mut array, &defaults);
+        assert_eq!(array, parse("[1]").unwrap());
+    }
+}
@@ -0,0 +1,334 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::pointer::array_index;
+
+/// A parsed JSON document.
+///
+/// Objects keep their entries in source order, so a document containing
+/// duplicate keys keeps every occurrence unless
+/// `ParseOptions::duplicate_keys` says otherwise.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+/// The type of a `JsonValue`, without its contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Null,
+    Boolean,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+impl ValueKind {
+    /// Lowercase name used in messages, e.g. "object".
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValueKind::Null => "null",
+            ValueKind::Boolean => "boolean",
+            ValueKind::Number => "number",
+            ValueKind::String => "string",
+            ValueKind::Array => "array",
+            ValueKind::Object => "object",
+        }
+    }
+}
+
+impl JsonValue {
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            JsonValue::Null => ValueKind::Null,
+            JsonValue::Boolean(_) => ValueKind::Boolean,
+            JsonValue::Number(_) => ValueKind::Number,
+            JsonValue::String(_) => ValueKind::String,
+            JsonValue::Array(_) => ValueKind::Array,
+            JsonValue::Object(_) => ValueKind::Object,
+        }
+    }
+
+    /// Looks up `key` in an object, returning the last occurrence if the key
+    /// is duplicated. Returns `None` for non-objects.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(entries) => entries.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Looks up a dotted path such as `users[0].name`: `.` separates
+    /// object keys and `[n]` indexes into an array. The empty path is the
+    /// value itself.
+    ///
+    /// Keys containing `.`, `[` or `]` can't be spelled this way; use
+    /// `pointer` for those. Duplicate keys resolve as in `get`.
+    pub fn get_path<'a>(&'a self, path: &str) -> Option<&'a JsonValue> {
+        if path.is_empty() {
+            return Some(self);
+        }
+        let mut current = self;
+        for (i, part) in path.split('.').enumerate() {
+            let (key, mut indices) = part.split_at(part.find('[').unwrap_or(part.len()));
+            if !key.is_empty() {
+                current = current.get(key)?;
+            } else if i > 0 || indices.is_empty() {
+                return None;
+            }
+            while !indices.is_empty() {
+                let (index, rest) = indices.strip_prefix('[')?.split_once(']')?;
+                current = match current {
+                    JsonValue::Array(elements) => elements.get(array_index(index)?)?,
+                    _ => return None,
+                };
+                indices = rest;
+            }
+        }
+        Some(current)
+    }
+
+    /// Consumes an object and returns its entries in source order, without
+    /// cloning them. Returns `None` for non-objects.
+    pub fn into_object_entries(self) -> Option<Vec<(String, JsonValue)>> {
+        match self {
+            JsonValue::Object(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    /// Consumes an array and returns its elements. Returns `None` for
+    /// non-arrays.
+    pub fn into_array(self) -> Option<Vec<JsonValue>> {
+        match self {
+            JsonValue::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    /// Keeps only the object entries for which `f(key, value)` returns
+    /// true, in their original order. Only this object's own entries are
+    /// looked at, not nested ones; anything other than an object is left
+    /// unchanged.
+    pub fn retain<F: FnMut(&str, &JsonValue) -> bool>(&mut self, mut f: F) {
+        if let JsonValue::Object(entries) = self {
+            entries.retain(|(key, value)| f(key, value));
+        }
+    }
+
+    /// Like `retain`, for arrays: keeps the elements for which
+    /// `f(index, element)` returns true, where `index` is the element's
+    /// position before filtering.
+    pub fn retain_elements<F: FnMut(usize, &JsonValue) -> bool>(&mut self, mut f: F) {
+        if let JsonValue::Array(elements) = self {
+            let mut index = 0;
+            elements.retain(|element| {
+                let keep = f(index, element);
+                index += 1;
+                keep
+            });
+        }
+    }
+
+    /// Replaces every string in the tree with `f` applied to it, e.g. to
+    /// trim or redact text. Object keys are only rewritten when
+    /// `include_keys` is set.
+    pub fn map_strings<F: FnMut(&str) -> String>(&mut self, include_keys: bool, mut f: F) {
+        let mut pending = alloc::vec![self];
+        while let Some(value) = pending.pop() {
+            match value {
+                JsonValue::String(s) => *s = f(s),
+                JsonValue::Array(elements) => pending.extend(elements.iter_mut().rev()),
+                JsonValue::Object(entries) => {
+                    if include_keys {
+                        for (key, _) in entries.iter_mut() {
+                            *key = f(key);
+                        }
+                    }
+                    pending.extend(entries.iter_mut().rev().map(|(_, child)| child));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// A copy cut down for logging: strings longer than `max_string_len`
+    /// characters keep that many followed by `...`, and arrays longer than
+    /// `max_array_len` keep that many elements followed by a string such
+    /// as `"...(+3 more)"`. Applies at every level; `self` is unchanged.
+    pub fn preview(&self, max_string_len: usize, max_array_len: usize) -> JsonValue {
+        match self {
+            JsonValue::String(s) => match s.char_indices().nth(max_string_len) {
+                Some((cut, _)) => JsonValue::String(format!("{}...", &s[..cut])),
+                None => self.clone(),
+            },
+            JsonValue::Array(elements) => {
+                let mut shown: Vec<JsonValue> = elements.iter().take(max_array_len).map(|e| e.preview(max_string_len, max_array_len)).collect();
+                if elements.len() > max_array_len {
+                    shown.push(JsonValue::String(format!("...(+{} more)", elements.len() - max_array_len)));
+                }
+                JsonValue::Array(shown)
+            }
+            JsonValue::Object(entries) => {
+                JsonValue::Object(entries.iter().map(|(k, v)| (k.clone(), v.preview(max_string_len, max_array_len))).collect())
+            }
+            scalar => scalar.clone(),
+        }
+    }
+
+    /// A short human-readable rendering, for logs rather than output: a
+    /// string's own text without quotes or escapes, the JSON literal for
+    /// other scalars, and a summary such as `<object:3 keys>` or
+    /// `<array:5>` for containers.
+    pub fn coerce_to_string(&self) -> String {
+        match self {
+            JsonValue::String(s) => s.clone(),
+            JsonValue::Array(elements) => format!("<array:{}>", elements.len()),
+            JsonValue::Object(entries) if entries.len() == 1 => "<object:1 key>".to_string(),
+            JsonValue::Object(entries) => format!("<object:{} keys>", entries.len()),
+            scalar => crate::to_string(scalar),
+        }
+    }
+
+    /// Moves the value out, leaving `Null` in its place (like
+    /// `Option::take`).
+    pub fn take(&mut self) -> JsonValue {
+        core::mem::replace(self, JsonValue::Null)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, pointer_mut};
+
+    #[test]
+    fn test_take_nested_array() {
+        let mut doc = parse(r#"{"a": {"items": [1, 2]}, "b": true}"#).unwrap();
+        let items = pointer_mut(&mut doc, "/a/items").unwrap().take();
+        assert_eq!(items, JsonValue::Array(vec![JsonValue::Number(1.0), JsonValue::Number(2.0)]));
+        assert_eq!(doc, parse(r#"{"a": {"items": null}, "b": true}"#).unwrap());
+    }
+
+    #[test]
+    fn test_get_path() {
+        let doc = parse(r#"{"users": [{"name": "ana", "tags": [["x", "y"]]}], "meta": {"page": {"size": 10}}}"#).unwrap();
+        assert_eq!(doc.get_path("users[0].name"), Some(&JsonValue::String("ana".into())));
+        assert_eq!(doc.get_path("users[0].tags[0][1]"), Some(&JsonValue::String("y".into())));
+        assert_eq!(doc.get_path("meta.page.size"), Some(&JsonValue::Number(10.0)));
+        assert_eq!(doc.get_path(""), Some(&doc));
+
+        let list = parse(r#"[[1, 2], {"a": 3}]"#).unwrap();
+        assert_eq!(list.get_path("[1].a"), Some(&JsonValue::Number(3.0)));
+        assert_eq!(list.get_path("[0][1]"), Some(&JsonValue::Number(2.0)));
+    }
+
+    #[test]
+    fn test_get_path_missing() {
+        let doc = parse(r#"{"users": [{"name": "ana"}], "a.b": 1}"#).unwrap();
+        assert_eq!(doc.get_path("users[1].name"), None);
+        assert_eq!(doc.get_path("users.name"), None);
+        assert_eq!(doc.get_path("users[0].email"), None);
+        assert_eq!(doc.get_path("users[x]"), None);
+        assert_eq!(doc.get_path("users[0"), None);
+        assert_eq!(doc.get_path("users..name"), None);
+        // Dotted keys can't be reached
+        assert_eq!(doc.get_path("a.b"), None);
+    }
+
+    #[test]
+    fn test_into_object_entries_and_array() {
+        let doc = parse(r#"{"name": "ana", "ids": [1, 2], "name": "bo"}"#).unwrap();
+        let entries = doc.into_object_entries().unwrap();
+        let keys: Vec<&str> = entries.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, vec!["name", "ids", "name"]);
+
+        let (_, ids) = entries.into_iter().nth(1).unwrap();
+        assert_eq!(ids.into_array().unwrap(), vec![JsonValue::Number(1.0), JsonValue::Number(2.0)]);
+    }
+
+    #[test]
+    fn test_into_conversions_reject_other_kinds() {
+        assert_eq!(JsonValue::Number(1.0).into_object_entries(), None);
+        assert_eq!(JsonValue::String("x".into()).into_array(), None);
+        assert_eq!(parse("[1]").unwrap().into_object_entries(), None);
+        assert_eq!(parse(r#"{"a": 1}"#).unwrap().into_array(), None);
+    }
+
+    #[test]
+    fn test_retain_drops_null_fields() {
+        let mut doc = parse(r#"{"a": null, "b": 1, "c": {"d": null}, "e": null, "f": "x"}"#).unwrap();
+        doc.retain(|_, value| *value != JsonValue::Null);
+        // Nested objects aren't touched
+        assert_eq!(doc, parse(r#"{"b": 1, "c": {"d": null}, "f": "x"}"#).unwrap());
+
+        doc.retain(|key, _| key != "c");
+        assert_eq!(doc, parse(r#"{"b": 1, "f": "x"}"#).unwrap());
+
+        let mut scalar = JsonValue::Number(1.0);
+        scalar.retain(|_, _| false);
+        assert_eq!(scalar, JsonValue::Number(1.0));
+    }
+
+    #[test]
+    fn test_retain_elements() {
+        let mut doc = parse(r#"[1, "two", 3, null, 5, 6]"#).unwrap();
+        doc.retain_elements(|_, element| matches!(element, JsonValue::Number(n) if *n > 2.0));
+        assert_eq!(doc, parse("[3, 5, 6]").unwrap());
+
+        doc.retain_elements(|index, _| index % 2 == 0);
+        assert_eq!(doc, parse("[3, 6]").unwrap());
+    }
+
+    #[test]
+    fn test_coerce_to_string() {
+        let coerce = |input: &str| parse(input).unwrap().coerce_to_string();
+        assert_eq!(coerce(r#""say \"hi\"""#), r#"say "hi""#);
+        assert_eq!(coerce("42"), "42");
+        assert_eq!(coerce("-2.5"), "-2.5");
+        assert_eq!(coerce("true"), "true");
+        assert_eq!(coerce("null"), "null");
+        assert_eq!(coerce(r#"{"a": 1, "b": {}, "c": []}"#), "<object:3 keys>");
+        assert_eq!(coerce(r#"{"a": 1}"#), "<object:1 key>");
+        assert_eq!(coerce("[1, [2, 3], 4, 5, 6]"), "<array:5>");
+        assert_eq!(coerce("[]"), "<array:0>");
+    }
+
+    #[test]
+    fn test_map_strings() {
+        let mut doc = parse(r#"{"name": "ana", "tags": ["a", {"note": "hi"}], "n": 1, "ok": null}"#).unwrap();
+        doc.map_strings(false, |s| s.to_uppercase());
+        assert_eq!(doc, parse(r#"{"name": "ANA", "tags": ["A", {"note": "HI"}], "n": 1, "ok": null}"#).unwrap());
+
+        doc.map_strings(true, |s| format!("<{}>", s));
+        assert_eq!(doc, parse(r#"{"<name>": "<ANA>", "<tags>": ["<A>", {"<note>": "<HI>"}], "<n>": 1, "<ok>": null}"#).unwrap());
+
+        // Strings are visited in document order
+        let mut seen = Vec::new();
+        parse(r#"["a", {"k": "b", "l": ["c"]}, "d"]"#).unwrap().map_strings(false, |s| {
+            seen.push(s.to_string());
+            s.to_string()
+        });
+        assert_eq!(seen, ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_preview() {
+        let doc = parse(r#"{"bio": "a very long biography", "name": "ana", "ids": [1, 2, 3, 4, 5, 6], "nested": [["abcdef", "ok"], []]}"#).unwrap();
+        let original = doc.clone();
+        assert_eq!(doc.preview(6, 3), parse(r#"{"bio": "a very...", "name": "ana", "ids": [1, 2, 3, "...(+3 more)"], "nested": [["abcdef", "ok"], []]}"#).unwrap());
+        assert_eq!(doc.preview(2, 1), parse(r#"{"bio": "a ...", "name": "an...", "ids": [1, "...(+5 more)"], "nested": [["ab...", "...(+1 more)"], "...(+1 more)"]}"#).unwrap());
+        assert_eq!(doc, original);
+
+        // Lengths count characters, not bytes
+        assert_eq!(JsonValue::String("héllo".to_string()).preview(2, 0), JsonValue::String("hé...".to_string()));
+        assert_eq!(JsonValue::Number(1.5).preview(0, 0), JsonValue::Number(1.5));
+    }
+}
@@ -0,0 +1,116 @@
+//! Non-fatal diagnostics for documents that parse but look suspicious.
+
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::error::ParseError;
+use crate::lexer::{Lexer, Position, Token};
+use crate::options::ParseOptions;
+use crate::parse_tokens;
+use crate::parser::ParseMode;
+use crate::value::JsonValue;
+
+/// Containers nested deeper than this get a warning from
+/// `parse_with_warnings`.
+pub const DEEP_NESTING: usize = 64;
+
+/// Something questionable in a document that still parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub message: String,
+    pub position: Position,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}, column {}", self.message, self.position.line, self.position.column)
+    }
+}
+
+/// Parses `input` as standard JSON and also returns warnings about things
+/// that are allowed but often mistakes, in document order:
+///
+/// - an object key repeated in the same object ("Duplicate key `a`"),
+///   which the default options keep rather than reject,
+/// - a number too large for `f64`, such as `1e400`, which becomes infinite,
+/// - nesting deeper than `DEEP_NESTING` levels, reported once at the first
+///   container past the limit.
+///
+/// Errors are the same as from `parse`.
+pub fn parse_with_warnings(input: &str) -> Result<(JsonValue, Vec<Warning>), ParseError> {
+    let (tokens, positions) = Lexer::new(input).lex_with_positions()?;
+
+    let mut warnings = Vec::new();
+    let mut warn = |message: String, position: Position| warnings.push(Warning { message, position });
+    // Keys seen so far in each open container; `None` for arrays
+    let mut open: Vec<Option<BTreeSet<&str>>> = Vec::new();
+    let mut warned_depth = false;
+    for (i, token) in tokens.iter().enumerate() {
+        let position = positions[i];
+        match token {
+            Token::LeftBrace | Token::LeftBracket => {
+                open.push(if *token == Token::LeftBrace { Some(BTreeSet::new()) } else { None });
+                if open.len() > DEEP_NESTING && !warned_depth {
+                    warn(format!("Nesting deeper than {} levels", DEEP_NESTING), position);
+                    warned_depth = true;
+                }
+            }
+            Token::RightBrace | Token::RightBracket => {
+                open.pop();
+            }
+            Token::String(key) if tokens.get(i + 1) == Some(&Token::Colon) => {
+                if let Some(Some(keys)) = open.last_mut() {
+                    if !keys.insert(key.as_str()) {
+                        warn(format!("Duplicate key `{}`", key), position);
+                    }
+                }
+            }
+            Token::Number(n) if !n.is_finite() => warn("Number out of range".into(), position),
+            _ => {}
+        }
+    }
+
+    let value = parse_tokens(tokens, positions, &ParseOptions::default(), ParseMode::BuildValue)?;
+    Ok((value, warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_duplicate_key_warning() {
+        let input = "{\"a\": 1,\n \"b\": {\"a\": 2},\n \"a\": 3}";
+        let (value, warnings) = parse_with_warnings(input).unwrap();
+        assert_eq!(value, parse(input).unwrap());
+        assert_eq!(warnings, vec![Warning { message: "Duplicate key `a`".into(), position: Position { line: 3, column: 2, offset: 26 } }]);
+        assert_eq!(warnings[0].to_string(), "Duplicate key `a` at line 3, column 2");
+    }
+
+    #[test]
+    fn test_range_and_depth_warnings() {
+        let (_, warnings) = parse_with_warnings("[1e400, -1e400, 1e300]").unwrap();
+        let columns: Vec<usize> = warnings.iter().map(|w| w.position.column).collect();
+        assert_eq!(columns, vec![2, 9]);
+        assert_eq!(warnings[0].message, "Number out of range");
+
+        let deep = format!("{}{}", "[".repeat(DEEP_NESTING + 2), "]".repeat(DEEP_NESTING + 2));
+        let (_, warnings) = parse_with_warnings(&deep).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, "Nesting deeper than 64 levels");
+        assert_eq!(warnings[0].position.column, DEEP_NESTING + 1);
+
+        let shallow = format!("{}{}", "[".repeat(DEEP_NESTING), "]".repeat(DEEP_NESTING));
+        assert!(parse_with_warnings(&shallow).unwrap().1.is_empty());
+        assert!(parse_with_warnings(r#"{"a": [1, 2], "b": {"a": 1}}"#).unwrap().1.is_empty());
+    }
+
+    #[test]
+    fn test_errors_still_fail() {
+        assert_eq!(parse_with_warnings(r#"{"a": 1, "a": }"#).unwrap_err().message(), "Expected value");
+    }
+}
@@ -1,7 +1,15 @@
+use std::collections::{HashMap, HashSet};
 use std::env; //Access command line arguments
 use std::fs;  //File System operations
-use std::io::{self, Read};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
 use std::process; //Program exit functionality
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+#[cfg(feature = "regex")]
+use regex::Regex;
 
 struct Counts {
     lines: usize,
@@ -13,60 +21,838 @@ struct Counts {
 enum Input {
     File(String),
     Stdin,
+    // Contents already read into memory
+    Bytes(Vec<u8>),
+}
+
+impl Input {
+    // Name shown next to the counts; stdin has none
+    fn label(&self) -> Option<&str> {
+        match self {
+            Input::File(path) => Some(path),
+            Input::Stdin | Input::Bytes(_) => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Encoding {
+    Utf8,
+    Latin1,
+}
+
+// Comment marker for --code-lines when --comment-prefix isn't given
+const DEFAULT_COMMENT_PREFIX: &str = "//";
+
+// Columns that --columns can pick from
+const COLUMN_NAMES: [&str; 4] = ["lines", "words", "bytes", "chars"];
+
+// Flags that pick what to count; at most one may be given
+const MODES: [&str; 16] = ["-c", "-l", "-w", "-m", "--sentences", "--max-columns", "--check-utf8", "--longest-token", "--avg-word-len", "--bom", "--unique-words", "--count-indented", "--empty-lines", "--code-lines", "--words-per-line", "--line-endings"];
+
+struct Config {
+    flag: Option<String>,
+    min_word_len: usize,
+    human: bool,
+    field_sep: String,
+    comment_prefix: Option<String>,
+    line_len_min: Option<usize>,
+    line_len_max: Option<usize>,
+    jsonl: bool,
+    csv: bool,
+    total: bool,
+    progress: bool,
+    encoding: Encoding,
+    columns: Vec<&'static str>,
+    jobs: usize,
+    exclude_bom: bool,
+    ignore_case: bool,
+    strip_punct: bool,
+    tabs_vs_spaces: bool,
+    count_byte: Option<u8>,
+    split_on_formfeed: bool,
+    tar: bool,
+    cache_duplicates: bool,
+    stdin_name: Option<String>,
+    output: Option<String>,
+    baseline: Option<String>,
+    byte_offset: Option<u64>,
+    byte_length: Option<u64>,
+    #[cfg(feature = "regex")]
+    regex: Option<Regex>,
+    inputs: Vec<Input>,
+}
+
+fn parse_args(args: &[String]) -> Result<Config, String> {
+    let usage = format!("Usage: {} [-c|-l|-w|-m|--sentences|--max-columns|--check-utf8|--longest-token|--avg-word-len|--bom|--unique-words|--count-indented [--tabs-vs-spaces]|--empty-lines|--code-lines [--comment-prefix=P]|--words-per-line|--line-endings] [--min-word-len=N] [--human] [--field-sep=SEP] [--line-len-min=N] [--line-len-max=N] [--regex=PATTERN] [--count-byte=N] [--jsonl [--total]|--csv|--baseline=FILE] [--progress] [--split-on-formfeed|--tar] [--encoding=utf8|latin1] [--columns=NAME,...] [--jobs=N] [--cache-duplicates] [--stdin-name=NAME] [--output=FILE] [--exclude-bom] [--ignore-case] [--strip-punct] [--byte-offset=N] [--byte-length=N] [file...]", args[0]);
+    let mut config = Config {
+        flag: None,
+        min_word_len: 0,
+        human: false,
+        field_sep: "\t".to_string(),
+        comment_prefix: None,
+        line_len_min: None,
+        line_len_max: None,
+        jsonl: false,
+        csv: false,
+        total: false,
+        progress: false,
+        encoding: Encoding::Utf8,
+        columns: Vec::new(),
+        jobs: 1,
+        exclude_bom: false,
+        ignore_case: false,
+        strip_punct: false,
+        tabs_vs_spaces: false,
+        count_byte: None,
+        split_on_formfeed: false,
+        tar: false,
+        cache_duplicates: false,
+        stdin_name: None,
+        output: None,
+        baseline: None,
+        byte_offset: None,
+        byte_length: None,
+        #[cfg(feature = "regex")]
+        regex: None,
+        inputs: Vec::new(),
+    };
+
+    for arg in &args[1..] {
+        if let Some(value) = arg.strip_prefix("--min-word-len=") {
+            config.min_word_len = value.parse().map_err(|_| usage.clone())?;
+        } else if let Some(value) = arg.strip_prefix("--comment-prefix=") {
+            if value.is_empty() {
+                return Err(usage);
+            }
+            config.comment_prefix = Some(value.to_string());
+        } else if let Some(value) = arg.strip_prefix("--field-sep=") {
+            if value.is_empty() {
+                return Err(usage);
+            }
+            config.field_sep = value.to_string();
+        } else if let Some(value) = arg.strip_prefix("--line-len-min=") {
+            config.line_len_min = Some(value.parse().map_err(|_| usage.clone())?);
+        } else if let Some(value) = arg.strip_prefix("--line-len-max=") {
+            config.line_len_max = Some(value.parse().map_err(|_| usage.clone())?);
+        } else if let Some(pattern) = arg.strip_prefix("--regex=") {
+            set_regex(&mut config, pattern)?;
+            if config.flag.is_some() {
+                return Err(usage);
+            }
+            config.flag = Some("--regex".to_string());
+        } else if let Some(value) = arg.strip_prefix("--count-byte=") {
+            // Decimal, or hex with a 0x prefix
+            let byte = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+                Some(hex) => u8::from_str_radix(hex, 16),
+                None => value.parse(),
+            };
+            config.count_byte = Some(byte.map_err(|_| usage.clone())?);
+            if config.flag.is_some() {
+                return Err(usage);
+            }
+            config.flag = Some("--count-byte".to_string());
+        } else if let Some(value) = arg.strip_prefix("--encoding=") {
+            config.encoding = match value.to_lowercase().as_str() {
+                "utf8" | "utf-8" => Encoding::Utf8,
+                "latin1" | "iso-8859-1" => Encoding::Latin1,
+                _ => return Err(usage),
+            };
+        } else if let Some(value) = arg.strip_prefix("--columns=") {
+            for name in value.split(',') {
+                let column = COLUMN_NAMES.iter().find(|&&known| known == name)
+                    .ok_or_else(|| format!("Unknown column `{}` (expected one of {})", name, COLUMN_NAMES.join(", ")))?;
+                config.columns.push(column);
+            }
+            if config.flag.is_some() {
+                return Err(usage);
+            }
+            config.flag = Some("--columns".to_string());
+        } else if let Some(value) = arg.strip_prefix("--jobs=") {
+            config.jobs = value.parse().map_err(|_| usage.clone())?;
+            if config.jobs == 0 {
+                return Err(usage);
+            }
+        } else if let Some(value) = arg.strip_prefix("--baseline=") {
+            config.baseline = Some(value.to_string());
+        } else if let Some(value) = arg.strip_prefix("--byte-offset=") {
+            config.byte_offset = Some(value.parse().map_err(|_| usage.clone())?);
+        } else if let Some(value) = arg.strip_prefix("--byte-length=") {
+            config.byte_length = Some(value.parse().map_err(|_| usage.clone())?);
+        } else if arg == "--exclude-bom" {
+            config.exclude_bom = true;
+        } else if arg == "--ignore-case" {
+            config.ignore_case = true;
+        } else if arg == "--strip-punct" {
+            config.strip_punct = true;
+        } else if arg == "--tabs-vs-spaces" {
+            config.tabs_vs_spaces = true;
+        } else if arg == "--human" {
+            config.human = true;
+        } else if arg == "--jsonl" {
+            config.jsonl = true;
+        } else if arg == "--csv" {
+            config.csv = true;
+        } else if arg == "--total" {
+            config.total = true;
+        } else if arg == "--progress" {
+            config.progress = true;
+        } else if arg == "--split-on-formfeed" {
+            config.split_on_formfeed = true;
+        } else if let Some(value) = arg.strip_prefix("--output=") {
+            config.output = Some(value.to_string());
+        } else if let Some(value) = arg.strip_prefix("--stdin-name=") {
+            config.stdin_name = Some(value.to_string());
+        } else if arg == "--cache-duplicates" {
+            config.cache_duplicates = true;
+        } else if arg == "--tar" {
+            if !cfg!(feature = "tar") {
+                return Err("--tar needs rowc built with the `tar` feature".to_string());
+            }
+            config.tar = true;
+        } else if arg.starts_with('-') {
+            if config.flag.is_some() || !MODES.contains(&arg.as_str()) {
+                return Err(usage);
+            }
+            config.flag = Some(arg.clone());
+        } else {
+            config.inputs.push(Input::File(arg.clone()));
+        }
+    }
+
+    // A line length bound selects its own counting mode
+    if config.line_len_min.is_some() || config.line_len_max.is_some() {
+        if config.flag.is_some() {
+            return Err(usage);
+        }
+        config.flag = Some("--line-len".to_string());
+    }
+
+    // --total only changes JSON lines output
+    if config.total && !config.jsonl {
+        return Err(usage);
+    }
+    if config.csv && config.jsonl {
+        return Err(usage);
+    }
+    // Deltas have their own plain format
+    if config.baseline.is_some() && (config.jsonl || config.csv) {
+        return Err(usage);
+    }
+    // Both replace each input with the pieces inside it
+    if config.tar && config.split_on_formfeed {
+        return Err(usage);
+    }
+    // --comment-prefix only applies to --code-lines
+    if config.comment_prefix.is_some() && config.flag.as_deref() != Some("--code-lines") {
+        return Err(usage);
+    }
+    // --tabs-vs-spaces breaks down the --count-indented figure
+    if config.tabs_vs_spaces && config.flag.as_deref() != Some("--count-indented") {
+        return Err(usage);
+    }
+
+    if config.inputs.is_empty() {
+        config.inputs.push(Input::Stdin);
+    }
+    Ok(config)
+}
+
+#[cfg(feature = "regex")]
+fn set_regex(config: &mut Config, pattern: &str) -> Result<(), String> {
+    let regex = Regex::new(pattern).map_err(|e| format!("Invalid regex: {}", e))?;
+    config.regex = Some(regex);
+    Ok(())
+}
+
+#[cfg(not(feature = "regex"))]
+fn set_regex(_config: &mut Config, _pattern: &str) -> Result<(), String> {
+    Err("--regex needs rowc built with the `regex` feature".to_string())
 }
 
 fn main() {
     let args: Vec::<String> = env::args().collect();
 
-    let (flag, input) = match args.len() {
-        1 => (None, Input::Stdin),
-        2 => {
-            if args[1].starts_with('-') {
-                (Some(args[1].as_str()), Input::Stdin)
-            } else {
-                (None, Input::File(args[1].clone()))
-            }
-        },
-        3 => (Some(args[1].as_str()), Input::File(args[2].clone())),
-        _ => {
-            eprintln!("Usage: {} [-c|-l|-w|-m] [file]", args[0]);
+    let config = match parse_args(&args) {
+        Ok(config) => config,
+        Err(usage) => {
+            eprintln!("{}", usage);
             process::exit(1);
         }
     };
 
-    if let Some(f) = flag {
-        if !vec!["-c", "-l", "-w", "-m"].contains(&f) {
-            eprintln!("Usage: {} [-c|-l|-w|-m] [file]", args[0]);
-            process::exit(1);
+    let ok = match &config.output {
+        Some(path) => run_to_file(&config, path).unwrap_or_else(|err| {
+            eprintln!("Error: cannot write {}: {}", path, err);
+            false
+        }),
+        None => run(&config, &mut io::stdout().lock()),
+    };
+    if !ok {
+        process::exit(1);
+    }
+}
+
+// Runs with the report going to `path` (for --output), which is created
+// or truncated first. Failing to create or write it is an error.
+fn run_to_file(config: &Config, path: &str) -> io::Result<bool> {
+    let mut out = io::BufWriter::new(fs::File::create(path)?);
+    let ok = run(config, &mut out);
+    out.flush()?;
+    Ok(ok)
+}
+
+// One reported figure: a plain count, a mean kept as its sum and number
+// of items so that totals can combine them, or a text label
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Stat {
+    Count(usize),
+    Mean { sum: usize, items: usize },
+    Label(&'static str),
+}
+
+impl Stat {
+    // Means get one decimal place; no items gives 0.0
+    fn render(&self) -> String {
+        match *self {
+            Stat::Count(value) => value.to_string(),
+            Stat::Mean { items: 0, .. } => "0.0".to_string(),
+            Stat::Mean { sum, items } => format!("{:.1}", sum as f64 / items as f64),
+            Stat::Label(label) => label.to_string(),
         }
     }
+}
+
+// Named figures for one input, in display order
+type Columns = Vec<(&'static str, Stat)>;
 
-    let result = match (flag, &input) {
-        (Some("-c"), input) => count_bytes(input.clone()).map(|c| format!("{:>8}", c)),
-        (Some("-l"), input) => count_lines(input.clone()).map(|c| format!("{:>8}", c)),
-        (Some("-w"), input) => count_words(input.clone()).map(|c| format!("{:>8}", c)),
-        (Some("-m"), input) => count_chars(input.clone()).map(|c| format!("{:>8}", c)),
-        (None, input) => count_all(input.clone()).map(|c| format!("{:>8} {:>8} {:>8}", c.lines, c.words, c.bytes)),
+fn count_columns(config: &Config, input: Input) -> io::Result<Columns> {
+    // Only the --byte-offset/--byte-length window is counted, as if it
+    // were the whole input
+    let input = if config.byte_offset.is_some() || config.byte_length.is_some() {
+        Input::Bytes(read_window(input, config.byte_offset.unwrap_or(0), config.byte_length)?)
+    } else {
+        input
+    };
+
+    // A leading byte order mark is dropped before anything is counted
+    let input = if config.exclude_bom && config.flag.as_deref() != Some("--bom") {
+        let mut bytes = read_to_bytes(input)?;
+        bytes.drain(..detect_bom(&bytes).map_or(0, |(_, len)| len));
+        Input::Bytes(bytes)
+    } else {
+        input
+    };
+
+    // Text in another encoding is decoded before counting, but byte
+    // counts keep reporting the size of the raw input
+    let byte_based = matches!(config.flag.as_deref(), Some("-c") | Some("--check-utf8") | Some("--bom") | Some("--count-byte"));
+    let (input, raw_len) = match config.encoding {
+        Encoding::Latin1 if !byte_based => {
+            let bytes = read_to_bytes(input)?;
+            (Input::Bytes(decode_latin1(&bytes).into_bytes()), Some(bytes.len()))
+        }
+        _ => (input, None),
+    };
+
+    let mut columns = match config.flag.as_deref() {
+        Some("-c") => vec![("bytes", Stat::Count(count_bytes(input)?))],
+        Some("-l") => vec![("lines", Stat::Count(count_lines(input)?))],
+        Some("-w") => vec![("words", Stat::Count(count_words(input, config.min_word_len)?))],
+        Some("-m") => vec![("chars", Stat::Count(count_chars(input)?))],
+        Some("--sentences") => vec![("sentences", Stat::Count(count_sentences(input)?))],
+        Some("--line-len") => vec![("lines", Stat::Count(count_lines_in_length_range(input, config.line_len_min.unwrap_or(0), config.line_len_max)?))],
+        Some("--max-columns") => vec![("max_columns", Stat::Count(count_max_columns(input, &config.field_sep)?))],
+        #[cfg(feature = "regex")]
+        Some("--regex") => vec![("lines", Stat::Count(count_matching_lines(input, config.regex.as_ref().unwrap())?))],
+        Some("--columns") => {
+            // Bytes are counted raw, as with -c; only the other columns
+            // need the input to be valid UTF-8
+            let bytes = read_to_bytes(input)?;
+            let contents = if config.columns.iter().any(|&name| name != "bytes") {
+                std::str::from_utf8(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+            } else {
+                ""
+            };
+            config.columns.iter().map(|&name| {
+                let value = match name {
+                    "lines" => contents.lines().count(),
+                    "words" => word_count(contents, config.min_word_len),
+                    "bytes" => bytes.len(),
+                    "chars" => contents.chars().count(),
+                    _ => unreachable!(),
+                };
+                (name, Stat::Count(value))
+            }).collect()
+        }
+        Some("--longest-token") => vec![("longest_token", Stat::Count(count_longest_word(input)?))],
+        Some("--avg-word-len") => {
+            let (sum, items) = count_word_chars(input, config.min_word_len)?;
+            vec![("avg_word_len", Stat::Mean { sum, items })]
+        }
+        Some("--bom") => vec![("bom", Stat::Label(detect_bom(&read_to_bytes(input)?).map_or("none", |(name, _)| name)))],
+        Some("--unique-words") => vec![("unique_words", Stat::Count(count_unique_words(input, config.ignore_case, config.strip_punct)?))],
+        Some("--count-indented") => {
+            let indentation = count_indentation(input)?;
+            let mut columns = vec![("indented", Stat::Count(indentation.indented)), ("max_indent", Stat::Count(indentation.max_indent))];
+            if config.tabs_vs_spaces {
+                columns.push(("tabs", Stat::Count(indentation.tabs)));
+                columns.push(("spaces", Stat::Count(indentation.spaces)));
+                columns.push(("mixed", Stat::Count(indentation.mixed)));
+            }
+            columns
+        }
+        Some("--empty-lines") => vec![("empty_lines", Stat::Count(count_empty_lines(input)?))],
+        Some("--words-per-line") => {
+            let words = words_per_line(&read_to_string(input)?, config.min_word_len);
+            vec![
+                ("min_words", Stat::Count(words.iter().copied().min().unwrap_or(0))),
+                ("max_words", Stat::Count(words.iter().copied().max().unwrap_or(0))),
+                ("avg_words", Stat::Mean { sum: words.iter().sum(), items: words.len() }),
+            ]
+        }
+        Some("--line-endings") => {
+            let endings = count_line_endings(&read_to_bytes(input)?);
+            vec![("lf", Stat::Count(endings.lf)), ("crlf", Stat::Count(endings.crlf)), ("cr", Stat::Count(endings.cr))]
+        }
+        Some("--code-lines") => {
+            let prefix = config.comment_prefix.as_deref().unwrap_or(DEFAULT_COMMENT_PREFIX);
+            vec![("code_lines", Stat::Count(count_code_lines(input, prefix)?))]
+        }
+        Some("--count-byte") => vec![("occurrences", Stat::Count(count_byte_occurrences(input, config.count_byte.unwrap())?))],
+        Some("--check-utf8") => vec![("utf8_errors", Stat::Count(count_utf8_errors(input)?))],
+        None if config.progress => {
+            let counts = count_all_with_progress(open_reader(input)?, config.min_word_len, PROGRESS_EVERY, |bytes| {
+                eprint!("\r{} bytes", bytes);
+            })?;
+            eprintln!();
+            vec![("lines", Stat::Count(counts.lines)), ("words", Stat::Count(counts.words)), ("bytes", Stat::Count(counts.bytes))]
+        }
+        None => {
+            let counts = count_all(input, config.min_word_len)?;
+            vec![("lines", Stat::Count(counts.lines)), ("words", Stat::Count(counts.words)), ("bytes", Stat::Count(counts.bytes))]
+        }
         _ => unreachable!(),
     };
 
-    match result {
-        Ok(output) => {
-            match input {
-                Input::File(path) => println!("{} {}", output, path),
-                Input::Stdin => println!("{}", output),
+    if let Some(len) = raw_len {
+        for (name, value) in columns.iter_mut() {
+            if *name == "bytes" {
+                *value = Stat::Count(len);
+            }
+        }
+    }
+    Ok(columns)
+}
+
+// The byte order mark `bytes` starts with, as its name and length
+fn detect_bom(bytes: &[u8]) -> Option<(&'static str, usize)> {
+    if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        Some(("utf-8", 3))
+    } else if bytes.starts_with(&[0xFF, 0xFE]) {
+        Some(("utf-16le", 2))
+    } else if bytes.starts_with(&[0xFE, 0xFF]) {
+        Some(("utf-16be", 2))
+    } else {
+        None
+    }
+}
+
+// Every ISO-8859-1 byte is the code point of the same value
+fn decode_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+// Counts every input and writes one line per input. Plain output gets a
+// "total" line when there are several inputs; JSON lines output only with
+// --total; CSV output always, after a header row. Unreadable inputs are
+// reported on stderr and make this return false, but the remaining inputs
+// are still counted. Stdin is unlabelled unless --stdin-name gives it a
+// name. With --split-on-formfeed each document in an input gets its own
+// line, labelled like "notes.txt#2" ("#2" on unnamed stdin), and with
+// --tar each regular file in an archive does, labelled with its path in
+// the archive and followed by a total. With --baseline each line shows
+// the change from the baseline file instead, and there is no total. Plain
+// --words-per-line output puts a "line words" row for each line of an
+// input before its summary.
+fn run(config: &Config, out: &mut impl Write) -> bool {
+    let mut ok = true;
+    let mut total: Option<Columns> = None;
+
+    let baseline = match &config.baseline {
+        Some(path) => match count_columns(config, Input::File(path.clone())) {
+            Ok(columns) => Some(columns),
+            Err(err) => {
+                report_error(Some(path), &err);
+                return false;
             }
         },
-        Err(err) => {
-            eprintln!("Error: {}", err);
-            process::exit(1);
+        None => None,
+    };
+
+    // Plain --words-per-line output lists every line before the summary,
+    // so stdin is read up front to allow reading it twice
+    let per_line = config.flag.as_deref() == Some("--words-per-line") && !config.jsonl && !config.csv && baseline.is_none();
+
+    let mut labels = Vec::new();
+    let mut inputs = Vec::new();
+    for input in &config.inputs {
+        let label = input_label(config, input);
+        if per_line && matches!(input, Input::Stdin) {
+            match read_to_bytes(Input::Stdin) {
+                Ok(bytes) => {
+                    labels.push(label.map(str::to_string));
+                    inputs.push(Input::Bytes(bytes));
+                }
+                Err(err) => {
+                    report_error(label, &err);
+                    ok = false;
+                }
+            }
+            continue;
+        }
+        if !config.split_on_formfeed && !config.tar {
+            labels.push(label.map(str::to_string));
+            inputs.push(input.clone());
+            continue;
+        }
+        let pieces = read_to_bytes(input.clone()).and_then(|bytes| {
+            if config.tar {
+                tar_entries(&bytes)
+            } else {
+                let label = label.unwrap_or("");
+                Ok(split_on_formfeed(&bytes).into_iter().enumerate().map(|(i, document)| (format!("{}#{}", label, i + 1), document)).collect())
+            }
+        });
+        match pieces {
+            Ok(pieces) => {
+                for (label, contents) in pieces {
+                    labels.push(Some(label));
+                    inputs.push(Input::Bytes(contents));
+                }
+            }
+            Err(err) => {
+                report_error(label, &err);
+                ok = false;
+            }
+        }
+    }
+
+    for ((label, input), result) in labels.iter().zip(&inputs).zip(count_inputs(config, &inputs)) {
+        let label = label.as_deref();
+        let columns = match result {
+            Ok(columns) => columns,
+            Err(err) => {
+                report_error(label, &err);
+                ok = false;
+                continue;
+            }
+        };
+        if config.csv && total.is_none() && writeln!(out, "{}", format_csv_header(&columns)).is_err() {
+            return false;
+        }
+        if per_line {
+            let contents = read_to_bytes(input.clone()).unwrap_or_default();
+            for (i, words) in words_per_line(&String::from_utf8_lossy(&contents), config.min_word_len).into_iter().enumerate() {
+                if writeln!(out, "{:>8} {:>8}", i + 1, words).is_err() {
+                    return false;
+                }
+            }
+        }
+        let line = if let Some(baseline) = &baseline {
+            format_delta(baseline, &columns, label)
+        } else if config.jsonl {
+            format_jsonl(&columns, label)
+        } else if config.csv {
+            format_csv(&columns, label)
+        } else {
+            format_plain(&columns, label, config.human)
+        };
+        if writeln!(out, "{}", line).is_err() {
+            return false;
+        }
+        total = Some(match total {
+            Some(total) => add_columns(total, &columns),
+            None => columns,
+        });
+    }
+
+    let show_total = if config.jsonl { config.total } else { baseline.is_none() && (config.csv || config.tar || inputs.len() > 1) };
+    if let Some(total) = total.filter(|_| show_total) {
+        let line = if config.jsonl {
+            format_jsonl(&total, Some("total"))
+        } else if config.csv {
+            format_csv(&total, Some("total"))
+        } else {
+            format_plain(&total, Some("total"), config.human)
+        };
+        if writeln!(out, "{}", line).is_err() {
+            return false;
+        }
+    }
+    ok
+}
+
+// The label an input is shown with: --stdin-name names stdin, which
+// otherwise has none
+fn input_label<'a>(config: &'a Config, input: &'a Input) -> Option<&'a str> {
+    match input {
+        Input::Stdin => config.stdin_name.as_deref(),
+        _ => input.label(),
+    }
+}
+
+fn report_error(label: Option<&str>, err: &io::Error) {
+    match label {
+        Some(label) => eprintln!("Error: {}: {}", label, err),
+        None => eprintln!("Error: {}", err),
+    }
+}
+
+// Form feed separated documents; a trailing form feed doesn't start
+// another (empty) one
+fn split_on_formfeed(bytes: &[u8]) -> Vec<Vec<u8>> {
+    let bytes = bytes.strip_suffix(b"\x0c").unwrap_or(bytes);
+    bytes.split(|&b| b == 0x0c).map(|document| document.to_vec()).collect()
+}
+
+// The regular files in a tar archive as (name, contents), in archive
+// order. Directories, links and other special entries are skipped.
+#[cfg(feature = "tar")]
+fn tar_entries(bytes: &[u8]) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let mut archive = tar::Archive::new(bytes);
+    let mut files = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
         }
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents)?;
+        files.push((name, contents));
     }
+    Ok(files)
+}
+
+// parse_args rejects --tar without the feature
+#[cfg(not(feature = "tar"))]
+fn tar_entries(_bytes: &[u8]) -> io::Result<Vec<(String, Vec<u8>)>> {
+    unreachable!()
+}
+
+// Counts every input, using up to `config.jobs` threads. Results come
+// back in argument order whatever order the threads finish in.
+fn count_inputs(config: &Config, inputs: &[Input]) -> Vec<io::Result<Columns>> {
+    count_inputs_with(config, inputs, |input| count_columns(config, input))
+}
+
+// count_inputs with the counting step passed in. With --cache-duplicates,
+// files whose canonical paths match (the same file given twice, or through
+// a symlink) are counted once and the result repeated for each of them.
+fn count_inputs_with<F>(config: &Config, inputs: &[Input], count: F) -> Vec<io::Result<Columns>>
+where F: Fn(Input) -> io::Result<Columns> + Sync {
+    if !config.cache_duplicates {
+        return count_in_parallel(config.jobs, inputs, &count);
+    }
+
+    let mut unique = Vec::new();
+    // Index into `unique` for each input
+    let mut slots = Vec::new();
+    let mut seen: HashMap<PathBuf, usize> = HashMap::new();
+    for input in inputs {
+        // Unreadable paths aren't cached, so each gets its own error
+        let path = match input {
+            Input::File(path) => fs::canonicalize(path).ok(),
+            Input::Stdin | Input::Bytes(_) => None,
+        };
+        if let Some(&slot) = path.as_ref().and_then(|path| seen.get(path)) {
+            slots.push(slot);
+            continue;
+        }
+        if let Some(path) = path {
+            seen.insert(path, unique.len());
+        }
+        slots.push(unique.len());
+        unique.push(input.clone());
+    }
+
+    let results = count_in_parallel(config.jobs, &unique, &count);
+    slots
+        .into_iter()
+        .map(|slot| match &results[slot] {
+            Ok(columns) => Ok(columns.clone()),
+            Err(err) => Err(io::Error::new(err.kind(), err.to_string())),
+        })
+        .collect()
+}
+
+fn count_in_parallel<F>(jobs: usize, inputs: &[Input], count: &F) -> Vec<io::Result<Columns>>
+where F: Fn(Input) -> io::Result<Columns> + Sync {
+    let jobs = jobs.min(inputs.len());
+    if jobs <= 1 {
+        return inputs.iter().map(|input| count(input.clone())).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<io::Result<Columns>>>> = Mutex::new(inputs.iter().map(|_| None).collect());
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(input) = inputs.get(index) else { break };
+                let result = count(input.clone());
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+    results.into_inner().unwrap().into_iter().map(|result| result.unwrap()).collect()
+}
+
+// Adds `columns` into a running total; maximums are kept rather than summed
+fn add_columns(mut total: Columns, columns: &Columns) -> Columns {
+    for ((name, acc), (_, stat)) in total.iter_mut().zip(columns) {
+        *acc = match (*acc, *stat) {
+            (Stat::Count(a), Stat::Count(b)) if ["max_columns", "longest_token", "max_indent", "max_words"].contains(name) => Stat::Count(a.max(b)),
+            (Stat::Count(a), Stat::Count(b)) if *name == "min_words" => Stat::Count(a.min(b)),
+            (Stat::Count(a), Stat::Count(b)) => Stat::Count(a + b),
+            (Stat::Mean { sum: a, items: m }, Stat::Mean { sum: b, items: n }) => Stat::Mean { sum: a + b, items: m + n },
+            // Labels don't add up to anything
+            (Stat::Label(_), Stat::Label(_)) => Stat::Label("-"),
+            _ => unreachable!("every input has the same columns"),
+        };
+    }
+    total
+}
+
+// Right-aligned counts followed by the label, like wc
+fn format_plain(columns: &Columns, label: Option<&str>, human: bool) -> String {
+    let mut line = columns
+        .iter()
+        .map(|&(name, stat)| match (name, stat) {
+            ("bytes", Stat::Count(value)) => format!("{:>8}", format_bytes(value, human)),
+            _ => format!("{:>8}", stat.render()),
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    if let Some(label) = label {
+        line.push(' ');
+        line.push_str(label);
+    }
+    line
+}
+
+// One JSON object, e.g. {"file":"a.txt","lines":3,"words":9,"bytes":40}.
+// Stdin has a null file.
+fn format_jsonl(columns: &Columns, label: Option<&str>) -> String {
+    let mut line = String::from("{\"file\":");
+    match label {
+        Some(label) => line.push_str(&json_string(label)),
+        None => line.push_str("null"),
+    }
+    for (name, stat) in columns {
+        let value = match stat {
+            Stat::Label(label) => json_string(label),
+            _ => stat.render(),
+        };
+        line.push_str(&format!(",\"{}\":{}", name, value));
+    }
+    line.push('}');
+    line
+}
+
+// Signed change per column from `baseline`, e.g. "+12 lines, -3 words,
+// +0 bytes new.txt". Means change by their rendered value; labels show
+// "old -> new".
+fn format_delta(baseline: &Columns, columns: &Columns, label: Option<&str>) -> String {
+    let mut line = baseline
+        .iter()
+        .zip(columns)
+        .map(|(&(name, before), &(_, after))| match (before, after) {
+            (Stat::Count(a), Stat::Count(b)) => format!("{:+} {}", b as i64 - a as i64, name),
+            (Stat::Mean { .. }, Stat::Mean { .. }) => {
+                let delta = after.render().parse::<f64>().unwrap() - before.render().parse::<f64>().unwrap();
+                format!("{:+.1} {}", delta, name)
+            }
+            _ => format!("{} {} -> {}", name, before.render(), after.render()),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    if let Some(label) = label {
+        line.push(' ');
+        line.push_str(label);
+    }
+    line
+}
+
+// "file" and then the column names, in the same order as format_csv
+fn format_csv_header(columns: &Columns) -> String {
+    let mut header = String::from("file");
+    for (name, _) in columns {
+        header.push(',');
+        header.push_str(name);
+    }
+    header
+}
+
+// One CSV record; stdin gets an empty file field
+fn format_csv(columns: &Columns, label: Option<&str>) -> String {
+    let mut line = csv_field(label.unwrap_or(""));
+    for (_, stat) in columns {
+        line.push(',');
+        line.push_str(&csv_field(&stat.render()));
+    }
+    line
+}
+
+// Quotes a field (doubling any quotes) when it holds a separator, quote
+// or line break, as RFC 4180 asks
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Renders a byte count, optionally as a base-1024 size like 1.5K or 3.4M
+fn format_bytes(bytes: usize, human: bool) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+
+    if !human || bytes < 1024 {
+        return bytes.to_string();
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    // Compare against the largest value that still rounds below 1024.0
+    while size >= 1023.95 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1}{}", size, UNITS[unit])
 }
 
 fn read_to_string(input: Input) -> io::Result<String> {
     match input {
         Input::File(path) => fs::read_to_string(path),
+        Input::Bytes(bytes) => String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
         Input::Stdin => {
             let mut buffer = String::new();
             io::stdin().read_to_string(&mut buffer)?;
@@ -78,6 +864,7 @@ fn read_to_string(input: Input) -> io::Result<String> {
 fn read_to_bytes(input: Input) -> io::Result<Vec<u8>> {
     match input {
         Input::File(path) => fs::read(path),
+        Input::Bytes(bytes) => Ok(bytes),
         Input::Stdin => {
             let mut buffer = Vec::new();
             io::stdin().read_to_end(&mut buffer)?;
@@ -86,19 +873,199 @@ fn read_to_bytes(input: Input) -> io::Result<Vec<u8>> {
     }
 }
 
+fn open_reader(input: Input) -> io::Result<Box<dyn Read>> {
+    match input {
+        Input::File(path) => Ok(Box::new(fs::File::open(path)?)),
+        Input::Stdin => Ok(Box::new(io::stdin())),
+        Input::Bytes(bytes) => Ok(Box::new(io::Cursor::new(bytes))),
+    }
+}
+
+// The `length` bytes starting `offset` bytes into the input (fewer at the
+// end, all the rest without a length). Files are seeked into; stdin has
+// the skipped bytes read and dropped. The window's edges fall wherever
+// they fall: a line cut at either edge still counts as a line, a word cut
+// at the start counts as a word, and a character cut in half makes text
+// counts fail as invalid UTF-8.
+fn read_window(input: Input, offset: u64, length: Option<u64>) -> io::Result<Vec<u8>> {
+    let mut reader: Box<dyn Read> = match input {
+        Input::File(path) => {
+            let mut file = fs::File::open(path)?;
+            file.seek(SeekFrom::Start(offset))?;
+            Box::new(file)
+        }
+        other => {
+            let mut reader = open_reader(other)?;
+            io::copy(&mut reader.by_ref().take(offset), &mut io::sink())?;
+            reader
+        }
+    };
+    let mut window = Vec::new();
+    match length {
+        Some(length) => reader.take(length).read_to_end(&mut window)?,
+        None => reader.read_to_end(&mut window)?,
+    };
+    Ok(window)
+}
+
+// Reads until `buffer` is full or the reader is exhausted
+fn read_chunk<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(filled)
+}
+
 fn count_bytes(input: Input) -> io::Result<usize> {
     let contents = read_to_bytes(input)?;
     Ok(contents.len())
 }
 
+// Lines with nothing at all on them; "   " has content and doesn't count
+fn count_empty_lines(input: Input) -> io::Result<usize> {
+    let contents = read_to_string(input)?;
+    Ok(contents.lines().filter(|line| line.is_empty()).count())
+}
+
+// Non-blank lines that aren't just a comment starting with `prefix` (after
+// any indentation). A line with code before a comment counts as code.
+fn count_code_lines(input: Input, prefix: &str) -> io::Result<usize> {
+    let contents = read_to_string(input)?;
+    Ok(contents.lines().map(str::trim_start).filter(|line| !line.is_empty() && !line.starts_with(prefix)).count())
+}
+
+struct LineEndings {
+    lf: usize,
+    crlf: usize,
+    cr: usize,
+}
+
+// How many lines end in each terminator. Works on bytes, since
+// str::lines treats "\r\n" and "\n" alike and doesn't split on a lone
+// "\r". A last line with no terminator isn't counted.
+fn count_line_endings(bytes: &[u8]) -> LineEndings {
+    let mut endings = LineEndings { lf: 0, crlf: 0, cr: 0 };
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1)) {
+            (b'\r', Some(b'\n')) => {
+                endings.crlf += 1;
+                i += 1;
+            }
+            (b'\r', _) => endings.cr += 1,
+            (b'\n', _) => endings.lf += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    endings
+}
+
+// Works on raw bytes, so the input needn't be text
+fn count_byte_occurrences(input: Input, byte: u8) -> io::Result<usize> {
+    let contents = read_to_bytes(input)?;
+    Ok(contents.iter().filter(|&&b| b == byte).count())
+}
+
 fn count_lines(input: Input) -> io::Result<usize> {
     let contents = read_to_string(input)?;
     Ok(contents.lines().count())
 }
 
-fn count_words(input: Input) -> io::Result<usize> {
+// Counts whitespace-delimited words at least `min_len` characters long
+fn word_count(contents: &str, min_len: usize) -> usize {
+    contents
+        .split_whitespace()
+        .filter(|word| word.chars().count() >= min_len)
+        .count()
+}
+
+// Word count of each line in turn; an empty line has 0
+fn words_per_line(contents: &str, min_len: usize) -> Vec<usize> {
+    contents.lines().map(|line| word_count(line, min_len)).collect()
+}
+
+fn count_words(input: Input, min_len: usize) -> io::Result<usize> {
     let contents = read_to_string(input)?;
-    Ok(contents.split_whitespace().count())
+    Ok(word_count(&contents, min_len))
+}
+
+// Length in characters of the longest whitespace-delimited token (0 if
+// there are no words)
+fn count_longest_word(input: Input) -> io::Result<usize> {
+    let contents = read_to_string(input)?;
+    Ok(contents
+        .split_whitespace()
+        .map(|word| word.chars().count())
+        .max()
+        .unwrap_or(0))
+}
+
+// Total characters across words and the number of words, for the mean
+// word length
+fn count_word_chars(input: Input, min_len: usize) -> io::Result<(usize, usize)> {
+    let contents = read_to_string(input)?;
+    Ok(contents
+        .split_whitespace()
+        .map(|word| word.chars().count())
+        .filter(|&len| len >= min_len)
+        .fold((0, 0), |(chars, words), len| (chars + len, words + 1)))
+}
+
+struct Indentation {
+    indented: usize,
+    // Widest leading run, in whitespace characters
+    max_indent: usize,
+    tabs: usize,
+    spaces: usize,
+    mixed: usize,
+}
+
+// Lines starting with spaces or tabs, split by what the indentation is
+// made of. Lines holding nothing but whitespace aren't counted.
+fn count_indentation(input: Input) -> io::Result<Indentation> {
+    let contents = read_to_string(input)?;
+    let mut indentation = Indentation { indented: 0, max_indent: 0, tabs: 0, spaces: 0, mixed: 0 };
+    for line in contents.lines() {
+        let body = line.trim_start_matches([' ', '\t']);
+        let indent = &line[..line.len() - body.len()];
+        if indent.is_empty() || body.is_empty() {
+            continue;
+        }
+        indentation.indented += 1;
+        indentation.max_indent = indentation.max_indent.max(indent.len());
+        if !indent.contains(' ') {
+            indentation.tabs += 1;
+        } else if !indent.contains('\t') {
+            indentation.spaces += 1;
+        } else {
+            indentation.mixed += 1;
+        }
+    }
+    Ok(indentation)
+}
+
+// Number of distinct whitespace-delimited words. Words are compared as
+// written, so "end" and "end." differ, unless `strip_punct` trims
+// non-alphanumeric characters from both ends (words that are nothing but
+// punctuation are then skipped). `ignore_case` compares lowercased words.
+fn count_unique_words(input: Input, ignore_case: bool, strip_punct: bool) -> io::Result<usize> {
+    let contents = read_to_string(input)?;
+    let mut seen = HashSet::new();
+    for word in contents.split_whitespace() {
+        let word = if strip_punct { word.trim_matches(|c: char| !c.is_alphanumeric()) } else { word };
+        if word.is_empty() {
+            continue;
+        }
+        seen.insert(if ignore_case { word.to_lowercase() } else { word.to_string() });
+    }
+    Ok(seen.len())
 }
 
 fn count_chars(input: Input) -> io::Result<usize> {
@@ -106,10 +1073,138 @@ fn count_chars(input: Input) -> io::Result<usize> {
     Ok(contents.chars().count())
 }
 
-fn count_all(input: Input) -> io::Result<Counts> {
+// Abbreviations whose trailing period doesn't end a sentence
+const ABBREVIATIONS: [&str; 10] = ["e.g.", "i.e.", "etc.", "vs.", "mr.", "mrs.", "ms.", "dr.", "st.", "jr."];
+
+// Experimental sentence counter. The heuristics are:
+// - a word whose text ends in '.', '!' or '?' ends a sentence, so a run of
+//   terminators like "?!" or "..." counts once
+// - closing quotes and brackets after the terminator are ignored ("Go.")
+// - words in ABBREVIATIONS (case-insensitive) never end a sentence, even
+//   when they really do at the end of a sentence
+// - text after the last terminator isn't counted as a sentence
+fn sentence_count(contents: &str) -> usize {
+    contents
+        .split_whitespace()
+        .map(|word| word.trim_end_matches(['"', '\'', ')', ']']))
+        .filter(|word| word.ends_with(['.', '!', '?']))
+        .filter(|word| !ABBREVIATIONS.contains(&word.to_lowercase().as_str()))
+        .count()
+}
+
+fn count_sentences(input: Input) -> io::Result<usize> {
+    let contents = read_to_string(input)?;
+    Ok(sentence_count(&contents))
+}
+
+// Counts lines whose length in characters is at least `min` and, when
+// given, at most `max`. Line terminators aren't part of the length.
+fn count_lines_in_length_range(input: Input, min: usize, max: Option<usize>) -> io::Result<usize> {
+    let contents = read_to_string(input)?;
+    Ok(contents
+        .lines()
+        .map(|line| line.chars().count())
+        .filter(|&len| len >= min && max.is_none_or(|max| len <= max))
+        .count())
+}
+
+// Lines containing a match for `regex` anywhere in them
+#[cfg(feature = "regex")]
+fn count_matching_lines(input: Input, regex: &Regex) -> io::Result<usize> {
+    let contents = read_to_string(input)?;
+    Ok(contents.lines().filter(|line| regex.is_match(line)).count())
+}
+
+// Largest number of `sep`-separated fields on any one line (0 for empty input)
+fn count_max_columns(input: Input, sep: &str) -> io::Result<usize> {
+    let contents = read_to_string(input)?;
+    Ok(contents
+        .lines()
+        .map(|line| line.split(sep).count())
+        .max()
+        .unwrap_or(0))
+}
+
+// Number of invalid UTF-8 sequences in the input (0 means it's clean).
+// Each maximal bad sequence counts once, the same units from_utf8_lossy
+// would replace with U+FFFD.
+fn count_utf8_errors(input: Input) -> io::Result<usize> {
+    let contents = read_to_bytes(input)?;
+    let mut rest = contents.as_slice();
+    let mut errors = 0;
+
+    while let Err(err) = std::str::from_utf8(rest) {
+        errors += 1;
+        // A truncated sequence at the very end has no error_len
+        let skip = err.valid_up_to() + err.error_len().unwrap_or(rest.len() - err.valid_up_to());
+        rest = &rest[skip..];
+    }
+    Ok(errors)
+}
+
+// How often --progress reports, in bytes
+const PROGRESS_EVERY: usize = 1 << 20;
+
+// Same counts as count_all, but reads `reader` in chunks of `every` bytes
+// instead of loading it whole, calling `progress` with the bytes processed
+// so far after each chunk (so ceil(len / every) times).
+fn count_all_with_progress<R: Read, F: FnMut(usize)>(mut reader: R, min_word_len: usize, every: usize, mut progress: F) -> io::Result<Counts> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8");
+    let mut buffer = vec![0; every.max(1)];
+    // Undecoded tail of a character split across chunks
+    let mut pending = Vec::new();
+    let mut counts = Counts { lines: 0, words: 0, bytes: 0 };
+    let mut word_len = 0;
+    let mut last = None;
+
+    loop {
+        let n = read_chunk(&mut reader, &mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        counts.bytes += n;
+        pending.extend_from_slice(&buffer[..n]);
+
+        let valid = match std::str::from_utf8(&pending) {
+            Ok(text) => text.len(),
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(_) => return Err(invalid()),
+        };
+        for c in std::str::from_utf8(&pending[..valid]).unwrap().chars() {
+            if c.is_whitespace() {
+                if word_len > 0 && word_len >= min_word_len {
+                    counts.words += 1;
+                }
+                word_len = 0;
+            } else {
+                word_len += 1;
+            }
+            if c == '\n' {
+                counts.lines += 1;
+            }
+            last = Some(c);
+        }
+        pending.drain(..valid);
+        progress(counts.bytes);
+    }
+
+    if !pending.is_empty() {
+        return Err(invalid());
+    }
+    if word_len > 0 && word_len >= min_word_len {
+        counts.words += 1;
+    }
+    // An unterminated last line still counts, as with str::lines
+    if last.is_some_and(|c| c != '\n') {
+        counts.lines += 1;
+    }
+    Ok(counts)
+}
+
+fn count_all(input: Input, min_word_len: usize) -> io::Result<Counts> {
     let contents = read_to_string(input)?;
-    let bytes = contents.as_bytes().len();
-    let words = contents.split_whitespace().count();
+    let bytes = contents.len();
+    let words = word_count(&contents, min_word_len);
     let lines = contents.lines().count();
 
     Ok(Counts {
@@ -125,6 +1220,13 @@ mod tests {
     use std::fs::File;
     use std::io::Write;
 
+    // Counts `path` with the columns selected by `args` (flags only)
+    fn columns_for(args: &[&str], path: &str) -> Columns {
+        let mut args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        args.insert(0, "rowc".to_string());
+        count_columns(&parse_args(&args).unwrap(), Input::File(path.to_string())).unwrap()
+    }
+
     #[test]
     fn test_count_chars() {
         let test_content = "Hello, 世界!\n";
@@ -136,7 +1238,7 @@ mod tests {
         let result = count_chars(Input::File(test_filename.to_string()));
         fs::remove_file(test_filename).unwrap();
         
-        assert_eq!(result.unwrap(), 9);
+        assert_eq!(result.unwrap(), 11);
     }
 
     #[test]
@@ -147,11 +1249,710 @@ mod tests {
         let mut file = File::create(test_filename).unwrap();
         file.write_all(test_content.as_bytes()).unwrap();
         
-        let result = count_all(Input::File(test_filename.to_string())).unwrap();
+        let result = count_all(Input::File(test_filename.to_string()), 0).unwrap();
         fs::remove_file(test_filename).unwrap();
         
         assert_eq!(result.lines, 3);
         assert_eq!(result.words, 9);
         assert_eq!(result.bytes, test_content.len());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_count_words_min_len() {
+        let test_content = "a an the word longer\nis it ok three\n";
+        let test_filename = "test_min_word_len.txt";
+
+        let mut file = File::create(test_filename).unwrap();
+        file.write_all(test_content.as_bytes()).unwrap();
+
+        let all = count_words(Input::File(test_filename.to_string()), 0).unwrap();
+        let at_least_3 = count_words(Input::File(test_filename.to_string()), 3).unwrap();
+        let counts = count_all(Input::File(test_filename.to_string()), 3).unwrap();
+        fs::remove_file(test_filename).unwrap();
+
+        assert_eq!(all, 9);
+        // "the" sits exactly on the threshold and counts
+        assert_eq!(at_least_3, 4);
+        assert_eq!(counts.words, 4);
+    }
+
+    #[test]
+    fn test_parse_args_min_word_len() {
+        let args: Vec<String> = ["rowc", "-w", "--min-word-len=3", "file.txt"].iter().map(|s| s.to_string()).collect();
+        let config = parse_args(&args).unwrap();
+        assert_eq!(config.flag.as_deref(), Some("-w"));
+        assert_eq!(config.min_word_len, 3);
+        assert!(matches!(config.inputs[0], Input::File(ref path) if path == "file.txt"));
+
+        let bad: Vec<String> = ["rowc", "--min-word-len=x"].iter().map(|s| s.to_string()).collect();
+        assert!(parse_args(&bad).is_err());
+    }
+
+    #[test]
+    fn test_sentence_count() {
+        let paragraph = "Pack light, e.g. a tent and a stove. Is that all?! Yes... \
+            Mr. Smith said \"go.\" Then i.e. nothing else happened";
+        assert_eq!(sentence_count(paragraph), 4);
+        assert_eq!(sentence_count(""), 0);
+        assert_eq!(sentence_count("No terminator here"), 0);
+    }
+
+    #[test]
+    fn test_count_sentences_file() {
+        let test_content = "First one. Second one!\nThird, e.g. with an abbreviation?\n";
+        let test_filename = "test_sentences.txt";
+
+        let mut file = File::create(test_filename).unwrap();
+        file.write_all(test_content.as_bytes()).unwrap();
+
+        let result = count_sentences(Input::File(test_filename.to_string()));
+        fs::remove_file(test_filename).unwrap();
+
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[test]
+    fn test_format_bytes_human() {
+        assert_eq!(format_bytes(1536, false), "1536");
+        assert_eq!(format_bytes(1023, true), "1023");
+        assert_eq!(format_bytes(1024, true), "1.0K");
+        assert_eq!(format_bytes(1536, true), "1.5K");
+        assert_eq!(format_bytes(1024 * 1024, true), "1.0M");
+        assert_eq!(format_bytes(3_565_158, true), "3.4M");
+        assert_eq!(format_bytes(6_012_954_214, true), "5.6G");
+        assert_eq!(format_bytes(1_048_575, true), "1.0M");
+        assert_eq!(format_bytes(1_048_524, true), "1023.9K");
+        assert_eq!(format_bytes((1 << 30) - 1, true), "1.0G");
+        assert_eq!(format_bytes((1 << 40) - 1, true), "1.0T");
+    }
+
+    #[test]
+    fn test_count_max_columns() {
+        let test_content = "id,name\n1,ana,admin,2024\n2,bo,user\n\n";
+        let test_filename = "test_max_columns.csv";
+
+        let mut file = File::create(test_filename).unwrap();
+        file.write_all(test_content.as_bytes()).unwrap();
+
+        let commas = count_max_columns(Input::File(test_filename.to_string()), ",");
+        let tabs = count_max_columns(Input::File(test_filename.to_string()), "\t");
+        fs::remove_file(test_filename).unwrap();
+
+        assert_eq!(commas.unwrap(), 4);
+        assert_eq!(tabs.unwrap(), 1);
+    }
+
+    #[test]
+    fn test_parse_args_field_sep() {
+        let args: Vec<String> = ["rowc", "--max-columns", "--field-sep=,", "f.csv"].iter().map(|s| s.to_string()).collect();
+        let config = parse_args(&args).unwrap();
+        assert_eq!(config.flag.as_deref(), Some("--max-columns"));
+        assert_eq!(config.field_sep, ",");
+    }
+
+    #[test]
+    fn test_count_lines_in_length_range() {
+        let test_content = format!("{}\n{}\n{}\r\nshort\n\n{}\n", "x".repeat(79), "x".repeat(80), "é".repeat(100), "y".repeat(120));
+        let test_filename = "test_line_len.txt";
+
+        let mut file = File::create(test_filename).unwrap();
+        file.write_all(test_content.as_bytes()).unwrap();
+
+        let over_80 = count_lines_in_length_range(Input::File(test_filename.to_string()), 80, None);
+        let between = count_lines_in_length_range(Input::File(test_filename.to_string()), 80, Some(100));
+        let up_to_5 = count_lines_in_length_range(Input::File(test_filename.to_string()), 0, Some(5));
+        fs::remove_file(test_filename).unwrap();
+
+        // Length is in chars, so the 100 two-byte chars count as 100
+        assert_eq!(over_80.unwrap(), 3);
+        assert_eq!(between.unwrap(), 2);
+        assert_eq!(up_to_5.unwrap(), 2);
+    }
+
+    #[test]
+    fn test_parse_args_line_len() {
+        let args: Vec<String> = ["rowc", "--line-len-min=80", "f.txt"].iter().map(|s| s.to_string()).collect();
+        let config = parse_args(&args).unwrap();
+        assert_eq!(config.flag.as_deref(), Some("--line-len"));
+        assert_eq!(config.line_len_min, Some(80));
+
+        let conflicting: Vec<String> = ["rowc", "-w", "--line-len-max=10"].iter().map(|s| s.to_string()).collect();
+        assert!(parse_args(&conflicting).is_err());
+    }
+
+    #[test]
+    fn test_jsonl_one_object_per_file() {
+        let first = "test_jsonl_a.txt";
+        let second = "test_jsonl_b.txt";
+        File::create(first).unwrap().write_all(b"one two\nthree\n").unwrap();
+        File::create(second).unwrap().write_all(b"four\n").unwrap();
+
+        let args: Vec<String> = ["rowc", "--jsonl", first, second].iter().map(|s| s.to_string()).collect();
+        let mut out = Vec::new();
+        let ok = run(&parse_args(&args).unwrap(), &mut out);
+
+        let with_total: Vec<String> = ["rowc", "--jsonl", "--total", "-l", first, second].iter().map(|s| s.to_string()).collect();
+        let mut total_out = Vec::new();
+        run(&parse_args(&with_total).unwrap(), &mut total_out);
+        fs::remove_file(first).unwrap();
+        fs::remove_file(second).unwrap();
+
+        assert!(ok);
+        assert_eq!(String::from_utf8(out).unwrap(),
+            "{\"file\":\"test_jsonl_a.txt\",\"lines\":2,\"words\":3,\"bytes\":14}\n\
+             {\"file\":\"test_jsonl_b.txt\",\"lines\":1,\"words\":1,\"bytes\":5}\n");
+        assert_eq!(String::from_utf8(total_out).unwrap().lines().last(), Some("{\"file\":\"total\",\"lines\":3}"));
+    }
+
+    #[test]
+    fn test_parse_args_total_needs_jsonl() {
+        let args: Vec<String> = ["rowc", "--total", "a.txt"].iter().map(|s| s.to_string()).collect();
+        assert!(parse_args(&args).is_err());
+    }
+
+    #[test]
+    fn test_json_string_escapes() {
+        assert_eq!(json_string("a \"b\"\\c\n"), "\"a \\\"b\\\"\\\\c\\n\"");
+    }
+
+    #[test]
+    fn test_count_utf8_errors() {
+        let clean_filename = "test_utf8_clean.txt";
+        let bad_filename = "test_utf8_bad.txt";
+        File::create(clean_filename).unwrap().write_all("Hello, 世界!\n".as_bytes()).unwrap();
+        // A stray continuation byte and a euro sign missing its last byte
+        File::create(bad_filename).unwrap().write_all(b"ok \x80 fine \xe2\x82 end\n").unwrap();
+
+        let clean = count_utf8_errors(Input::File(clean_filename.to_string()));
+        let bad = count_utf8_errors(Input::File(bad_filename.to_string()));
+        fs::remove_file(clean_filename).unwrap();
+        fs::remove_file(bad_filename).unwrap();
+
+        assert_eq!(clean.unwrap(), 0);
+        assert_eq!(bad.unwrap(), 2);
+    }
+
+    #[test]
+    fn test_count_all_with_progress() {
+        // Multibyte chars land on chunk boundaries, and the last line is unterminated
+        let text = "héllo wörld a\nsecond line here\n".repeat(300) + "tail";
+        let mut reports = Vec::new();
+        let counts = count_all_with_progress(text.as_bytes(), 2, 1024, |bytes| reports.push(bytes)).unwrap();
+
+        assert_eq!(reports.len(), text.len().div_ceil(1024));
+        assert_eq!(reports[0], 1024);
+        assert_eq!(reports.last(), Some(&text.len()));
+        assert_eq!(counts.bytes, text.len());
+        assert_eq!(counts.lines, text.lines().count());
+        assert_eq!(counts.words, word_count(&text, 2));
+
+        assert!(count_all_with_progress(&b"ok \xff"[..], 0, 2, |_| {}).is_err());
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_count_matching_lines() {
+        let test_content = "INFO start\nERROR disk full\nWARN slow\ninfo ERROR lowercase level\nDEBUG x\n";
+        let test_filename = "test_regex.log";
+
+        let mut file = File::create(test_filename).unwrap();
+        file.write_all(test_content.as_bytes()).unwrap();
+
+        let any = count_matching_lines(Input::File(test_filename.to_string()), &Regex::new("ERROR|WARN").unwrap());
+        let anchored = count_matching_lines(Input::File(test_filename.to_string()), &Regex::new("^(ERROR|WARN) ").unwrap());
+        let class = count_matching_lines(Input::File(test_filename.to_string()), &Regex::new("^[A-Z]{4,5} ").unwrap());
+        fs::remove_file(test_filename).unwrap();
+
+        assert_eq!(any.unwrap(), 3);
+        assert_eq!(anchored.unwrap(), 2);
+        assert_eq!(class.unwrap(), 4);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_parse_args_invalid_regex() {
+        let args: Vec<String> = ["rowc", "--regex=(ERROR", "f.log"].iter().map(|s| s.to_string()).collect();
+        assert!(parse_args(&args).err().unwrap().starts_with("Invalid regex:"));
+
+        let args: Vec<String> = ["rowc", "--regex=ERROR", "f.log"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(parse_args(&args).ok().unwrap().flag.as_deref(), Some("--regex"));
+    }
+
+    #[test]
+    fn test_latin1_encoding() {
+        // "café naïve\n" in ISO-8859-1: é and ï are single bytes
+        let test_content = b"caf\xe9 na\xefve\n";
+        let test_filename = "test_latin1.txt";
+        File::create(test_filename).unwrap().write_all(test_content).unwrap();
+
+        let latin1 = |flag: &str| columns_for(&["--encoding=latin1", flag], test_filename);
+        let chars = latin1("-m");
+        let bytes = latin1("-c");
+        let as_utf8 = count_chars(Input::File(test_filename.to_string()));
+        fs::remove_file(test_filename).unwrap();
+
+        assert_eq!(chars, vec![("chars", Stat::Count(11))]);
+        assert_eq!(bytes, vec![("bytes", Stat::Count(11))]);
+        assert!(as_utf8.is_err());
+        assert_eq!(decode_latin1(b"\xe9\xff"), "éÿ");
+    }
+
+    #[test]
+    fn test_columns_order() {
+        let test_content = "one two three\nfour\n";
+        let test_filename = "test_columns.txt";
+        File::create(test_filename).unwrap().write_all(test_content.as_bytes()).unwrap();
+
+        let args: Vec<String> = ["rowc", "--columns=words,lines,bytes", test_filename].iter().map(|s| s.to_string()).collect();
+        let mut out = Vec::new();
+        run(&parse_args(&args).unwrap(), &mut out);
+        fs::remove_file(test_filename).unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap(), format!("{:>8} {:>8} {:>8} {}\n", 4, 2, 19, test_filename));
+
+        // Binary input: bytes alone are counted like -c, text columns need UTF-8
+        let binary = "test_columns.bin";
+        File::create(binary).unwrap().write_all(b"\xff\xfe\x00\x80 data\n").unwrap();
+        let bytes_only = columns_for(&["--columns=bytes"], binary);
+        let with_c = columns_for(&["-c"], binary);
+        let with_lines = count_columns(&parse_args(&["rowc".to_string(), "--columns=bytes,lines".to_string()]).unwrap(), Input::File(binary.to_string()));
+        fs::remove_file(binary).unwrap();
+        assert_eq!(bytes_only, vec![("bytes", Stat::Count(10))]);
+        assert_eq!(bytes_only, with_c);
+        assert_eq!(with_lines.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let bad: Vec<String> = ["rowc", "--columns=words,pages"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(parse_args(&bad).err().unwrap(), "Unknown column `pages` (expected one of lines, words, bytes, chars)");
+    }
+
+    #[test]
+    fn test_count_longest_word() {
+        let test_content = format!("short words here\nvar a={};\n", "x".repeat(5000));
+        let test_filename = "test_longest_token.txt";
+        let empty_filename = "test_longest_token_empty.txt";
+        File::create(test_filename).unwrap().write_all(test_content.as_bytes()).unwrap();
+        File::create(empty_filename).unwrap();
+
+        let longest = count_longest_word(Input::File(test_filename.to_string()));
+        let empty = count_longest_word(Input::File(empty_filename.to_string()));
+        fs::remove_file(test_filename).unwrap();
+        fs::remove_file(empty_filename).unwrap();
+
+        assert_eq!(longest.unwrap(), 5003);
+        assert_eq!(empty.unwrap(), 0);
+    }
+
+    #[test]
+    fn test_avg_word_len() {
+        let test_content = "a bb ccc\ndddd é\n";
+        let test_filename = "test_avg_word_len.txt";
+        let empty_filename = "test_avg_word_len_empty.txt";
+        File::create(test_filename).unwrap().write_all(test_content.as_bytes()).unwrap();
+        File::create(empty_filename).unwrap();
+
+        let totals = count_word_chars(Input::File(test_filename.to_string()), 0);
+        let empty = count_word_chars(Input::File(empty_filename.to_string()), 0);
+        fs::remove_file(test_filename).unwrap();
+        fs::remove_file(empty_filename).unwrap();
+
+        let (sum, items) = totals.unwrap();
+        assert_eq!((sum, items), (11, 5));
+        assert_eq!(Stat::Mean { sum, items }.render(), "2.2");
+        let (sum, items) = empty.unwrap();
+        assert_eq!(Stat::Mean { sum, items }.render(), "0.0");
+    }
+
+    #[test]
+    fn test_jobs_match_sequential() {
+        let names: Vec<String> = (0..12).map(|i| format!("test_jobs_{}.txt", i)).collect();
+        for (i, name) in names.iter().enumerate() {
+            File::create(name).unwrap().write_all("word ".repeat(i * 100 + 1).as_bytes()).unwrap();
+        }
+
+        let output = |jobs: &str| {
+            let mut args = vec!["rowc".to_string(), jobs.to_string()];
+            args.extend(names.iter().cloned());
+            args.push("test_jobs_missing.txt".to_string());
+            let mut out = Vec::new();
+            let ok = run(&parse_args(&args).unwrap(), &mut out);
+            (ok, String::from_utf8(out).unwrap())
+        };
+        let sequential = output("--jobs=1");
+        let parallel = output("--jobs=4");
+        for name in &names {
+            fs::remove_file(name).unwrap();
+        }
+
+        assert_eq!(parallel, sequential);
+        assert!(!parallel.0);
+        assert_eq!(parallel.1.lines().count(), 13);
+        assert!(parallel.1.lines().next().unwrap().ends_with("test_jobs_0.txt"));
+    }
+
+    #[test]
+    fn test_bom_detection_and_exclusion() {
+        let with_bom = "test_bom_utf8.txt";
+        let without_bom = "test_bom_none.txt";
+        File::create(with_bom).unwrap().write_all(b"\xEF\xBB\xBFhi there\n").unwrap();
+        File::create(without_bom).unwrap().write_all(b"hi there\n").unwrap();
+
+        let bom = columns_for(&["--bom"], with_bom);
+        let no_bom = columns_for(&["--bom"], without_bom);
+        let bytes = columns_for(&["-c"], with_bom);
+        let bytes_excluded = columns_for(&["-c", "--exclude-bom"], with_bom);
+        let chars_excluded = columns_for(&["-m", "--exclude-bom"], with_bom);
+        let plain_excluded = columns_for(&["-c", "--exclude-bom"], without_bom);
+        fs::remove_file(with_bom).unwrap();
+        fs::remove_file(without_bom).unwrap();
+
+        assert_eq!(bom, vec![("bom", Stat::Label("utf-8"))]);
+        assert_eq!(no_bom, vec![("bom", Stat::Label("none"))]);
+        assert_eq!(bytes, vec![("bytes", Stat::Count(12))]);
+        assert_eq!(bytes_excluded, vec![("bytes", Stat::Count(9))]);
+        assert_eq!(chars_excluded, vec![("chars", Stat::Count(9))]);
+        assert_eq!(plain_excluded, vec![("bytes", Stat::Count(9))]);
+        assert_eq!(detect_bom(b"\xFF\xFEh\0"), Some(("utf-16le", 2)));
+    }
+
+    #[test]
+    fn test_count_unique_words() {
+        let test_content = "The cat saw the cat.\nTHE end -- the END\n";
+        let test_filename = "test_unique_words.txt";
+        File::create(test_filename).unwrap().write_all(test_content.as_bytes()).unwrap();
+
+        let unique = |ignore_case, strip_punct| count_unique_words(Input::File(test_filename.to_string()), ignore_case, strip_punct).unwrap();
+        let exact = unique(false, false);
+        let folded = unique(true, false);
+        let stripped = unique(false, true);
+        let both = unique(true, true);
+        fs::remove_file(test_filename).unwrap();
+
+        // The, cat, saw, the, cat., THE, end, --, END
+        assert_eq!(exact, 9);
+        // the, cat, saw, cat., end, --
+        assert_eq!(folded, 6);
+        // The, cat, saw, the, THE, end, END
+        assert_eq!(stripped, 7);
+        // the, cat, saw, end
+        assert_eq!(both, 4);
+    }
+
+    #[test]
+    fn test_byte_window() {
+        let test_content = "alpha beta\ngamma delta\nepsilon\n";
+        let test_filename = "test_byte_window.txt";
+        File::create(test_filename).unwrap().write_all(test_content.as_bytes()).unwrap();
+
+        // "ta\ngamma delta\nep": the cut-off "ta" and "ep" count as words
+        let window = columns_for(&["--byte-offset=8", "--byte-length=17"], test_filename);
+        let tail = columns_for(&["--byte-offset=23"], test_filename);
+        let head = columns_for(&["-c", "--byte-length=5"], test_filename);
+        let past_end = columns_for(&["--byte-offset=1000"], test_filename);
+        let from_stdin = read_window(Input::Bytes(test_content.as_bytes().to_vec()), 8, Some(17)).unwrap();
+        fs::remove_file(test_filename).unwrap();
+
+        assert_eq!(window, vec![("lines", Stat::Count(3)), ("words", Stat::Count(4)), ("bytes", Stat::Count(17))]);
+        assert_eq!(tail, vec![("lines", Stat::Count(1)), ("words", Stat::Count(1)), ("bytes", Stat::Count(8))]);
+        assert_eq!(head, vec![("bytes", Stat::Count(5))]);
+        assert_eq!(past_end, vec![("lines", Stat::Count(0)), ("words", Stat::Count(0)), ("bytes", Stat::Count(0))]);
+        assert_eq!(from_stdin, b"ta\ngamma delta\nep");
+    }
+
+    #[test]
+    fn test_count_indented() {
+        let test_content = "fn main() {\n    let a = 1;\n\tlet b = 2;\n\t  if a {\n        b\n\n   \n}\n";
+        let test_filename = "test_count_indented.txt";
+        File::create(test_filename).unwrap().write_all(test_content.as_bytes()).unwrap();
+
+        let indented = columns_for(&["--count-indented"], test_filename);
+        let split = columns_for(&["--count-indented", "--tabs-vs-spaces"], test_filename);
+        fs::remove_file(test_filename).unwrap();
+
+        // The whitespace-only line isn't indented code
+        assert_eq!(indented, vec![("indented", Stat::Count(4)), ("max_indent", Stat::Count(8))]);
+        assert_eq!(split, vec![
+            ("indented", Stat::Count(4)),
+            ("max_indent", Stat::Count(8)),
+            ("tabs", Stat::Count(1)),
+            ("spaces", Stat::Count(2)),
+            ("mixed", Stat::Count(1)),
+        ]);
+        assert!(parse_args(&["rowc".to_string(), "--tabs-vs-spaces".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_count_byte_occurrences() {
+        let test_filename = "test_count_byte.bin";
+        File::create(test_filename).unwrap().write_all(b"\x00\xff\n\x00\xc3\n\x00\x00rest\n").unwrap();
+
+        let newlines_hex = columns_for(&["--count-byte=0x0A"], test_filename);
+        let newlines_decimal = columns_for(&["--count-byte=10"], test_filename);
+        let nuls = columns_for(&["--count-byte=0"], test_filename);
+        fs::remove_file(test_filename).unwrap();
+
+        assert_eq!(newlines_hex, vec![("occurrences", Stat::Count(3))]);
+        assert_eq!(newlines_decimal, newlines_hex);
+        assert_eq!(nuls, vec![("occurrences", Stat::Count(4))]);
+        for bad in ["--count-byte=256", "--count-byte=0xZZ", "--count-byte="] {
+            assert!(parse_args(&["rowc".to_string(), bad.to_string()]).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_count_empty_lines() {
+        let test_content = "first\n\n   \n\t\nsecond\r\n\r\n\nlast";
+        let test_filename = "test_empty_lines.txt";
+        File::create(test_filename).unwrap().write_all(test_content.as_bytes()).unwrap();
+
+        let empty = count_empty_lines(Input::File(test_filename.to_string())).unwrap();
+        let columns = columns_for(&["--empty-lines"], test_filename);
+        fs::remove_file(test_filename).unwrap();
+
+        // The "   " and "\t" lines aren't empty
+        assert_eq!(empty, 3);
+        assert_eq!(columns, vec![("empty_lines", Stat::Count(3))]);
+    }
+
+    #[test]
+    fn test_split_on_formfeed() {
+        let test_filename = "test_split_on_formfeed.txt";
+        File::create(test_filename).unwrap().write_all(b"first doc\nhas two lines\n\x0csecond one\n\x0c").unwrap();
+
+        let args: Vec<String> = ["rowc", "--split-on-formfeed", test_filename].iter().map(|s| s.to_string()).collect();
+        let mut out = Vec::new();
+        let ok = run(&parse_args(&args).unwrap(), &mut out);
+        fs::remove_file(test_filename).unwrap();
+
+        assert!(ok);
+        assert_eq!(String::from_utf8(out).unwrap(),
+            "       2        5       24 test_split_on_formfeed.txt#1\n       \
+             1        2       11 test_split_on_formfeed.txt#2\n       \
+             3        7       35 total\n");
+        assert_eq!(split_on_formfeed(b"a\x0c\x0cb"), vec![b"a".to_vec(), Vec::new(), b"b".to_vec()]);
+        assert_eq!(split_on_formfeed(b""), vec![Vec::new()]);
+    }
+
+    #[test]
+    fn test_csv_output() {
+        let plain = "test_csv_plain.txt";
+        let awkward = "test_csv_a,\"b\".txt";
+        File::create(plain).unwrap().write_all(b"one two\nthree\n").unwrap();
+        File::create(awkward).unwrap().write_all("caf\u{e9}\n".as_bytes()).unwrap();
+
+        let args: Vec<String> = ["rowc", "--csv", "--columns=lines,words,bytes,chars", plain, awkward].iter().map(|s| s.to_string()).collect();
+        let mut out = Vec::new();
+        let ok = run(&parse_args(&args).unwrap(), &mut out);
+        fs::remove_file(plain).unwrap();
+        fs::remove_file(awkward).unwrap();
+
+        assert!(ok);
+        assert_eq!(String::from_utf8(out).unwrap(), "\
+file,lines,words,bytes,chars
+test_csv_plain.txt,2,3,14,14
+\"test_csv_a,\"\"b\"\".txt\",1,1,6,5
+total,3,4,20,19
+");
+        assert_eq!(csv_field("plain"), "plain");
+        assert!(parse_args(&["rowc".to_string(), "--csv".to_string(), "--jsonl".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_baseline_deltas() {
+        let old = "test_baseline_old.txt";
+        let new = "test_baseline_new.txt";
+        File::create(old).unwrap().write_all(b"one two three\nfour five\n").unwrap();
+        File::create(new).unwrap().write_all(b"one two\nthree\nfour\nsix seven\n").unwrap();
+
+        let baseline = format!("--baseline={}", old);
+        let args: Vec<String> = ["rowc", baseline.as_str(), new].iter().map(|s| s.to_string()).collect();
+        let mut out = Vec::new();
+        let ok = run(&parse_args(&args).unwrap(), &mut out);
+
+        let avg_args: Vec<String> = ["rowc", "--avg-word-len", baseline.as_str(), old].iter().map(|s| s.to_string()).collect();
+        let mut avg_out = Vec::new();
+        run(&parse_args(&avg_args).unwrap(), &mut avg_out);
+        fs::remove_file(old).unwrap();
+        fs::remove_file(new).unwrap();
+
+        // 2 lines, 5 words, 24 bytes before; 4 lines, 6 words, 29 bytes after
+        assert!(ok);
+        assert_eq!(String::from_utf8(out).unwrap(), "+2 lines, +1 words, +5 bytes test_baseline_new.txt\n");
+        assert_eq!(String::from_utf8(avg_out).unwrap(), "+0.0 avg_word_len test_baseline_old.txt\n");
+        let base = vec![("bom", Stat::Label("none")), ("lines", Stat::Count(5))];
+        let changed = vec![("bom", Stat::Label("utf-8")), ("lines", Stat::Count(2))];
+        assert_eq!(format_delta(&base, &changed, None), "bom none -> utf-8, -3 lines");
+    }
+
+    #[cfg(feature = "tar")]
+    #[test]
+    fn test_tar_entries() {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut add = |path: &str, kind: tar::EntryType, data: &[u8]| {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(kind);
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, path, data).unwrap();
+        };
+        add("docs/", tar::EntryType::Directory, b"");
+        add("docs/a.txt", tar::EntryType::Regular, b"one two\nthree\n");
+        add("docs/link", tar::EntryType::Symlink, b"");
+        add("b.txt", tar::EntryType::Regular, b"four\n");
+        let archive = builder.into_inner().unwrap();
+
+        let entries = tar_entries(&archive).unwrap();
+        assert_eq!(entries, vec![("docs/a.txt".to_string(), b"one two\nthree\n".to_vec()), ("b.txt".to_string(), b"four\n".to_vec())]);
+
+        let test_filename = "test_tar_entries.tar";
+        File::create(test_filename).unwrap().write_all(&archive).unwrap();
+        let args: Vec<String> = ["rowc", "--tar", test_filename].iter().map(|s| s.to_string()).collect();
+        let mut out = Vec::new();
+        let ok = run(&parse_args(&args).unwrap(), &mut out);
+        fs::remove_file(test_filename).unwrap();
+
+        assert!(ok);
+        assert_eq!(String::from_utf8(out).unwrap(),
+            "       2        3       14 docs/a.txt\n       \
+             1        1        5 b.txt\n       \
+             3        4       19 total\n");
+        let args: Vec<String> = ["rowc", "--tar", "--split-on-formfeed", "a.tar"].iter().map(|s| s.to_string()).collect();
+        assert!(parse_args(&args).is_err());
+    }
+
+    #[test]
+    fn test_cache_duplicates() {
+        let test_filename = "test_cache_duplicates.txt";
+        File::create(test_filename).unwrap().write_all(b"one two\nthree\n").unwrap();
+        let same_file = format!("./{}", test_filename);
+
+        let reads = |flags: &[&str]| {
+            let mut args: Vec<String> = ["rowc"].iter().chain(flags).map(|s| s.to_string()).collect();
+            args.extend([test_filename.to_string(), same_file.clone(), "test_cache_missing.txt".to_string()]);
+            let config = parse_args(&args).unwrap();
+            let opened = AtomicUsize::new(0);
+            let results = count_inputs_with(&config, &config.inputs, |input| {
+                if matches!(input, Input::File(_)) {
+                    opened.fetch_add(1, Ordering::Relaxed);
+                }
+                count_columns(&config, input)
+            });
+            assert_eq!(results.len(), 3);
+            assert_eq!(results[0].as_ref().unwrap(), results[1].as_ref().unwrap());
+            assert!(results[2].is_err());
+            opened.into_inner()
+        };
+        let cached = reads(&["--cache-duplicates"]);
+        let cached_parallel = reads(&["--cache-duplicates", "--jobs=2"]);
+        let plain = reads(&[]);
+        fs::remove_file(test_filename).unwrap();
+
+        // The missing file is still tried once
+        assert_eq!(cached, 2);
+        assert_eq!(cached_parallel, 2);
+        assert_eq!(plain, 3);
+    }
+
+    #[test]
+    fn test_count_code_lines() {
+        let test_content = "// Header comment\n\nfn main() {\n    // indented comment\n    let x = 1; // trailing comment\n   \n\t//tab-indented\n}\n# shell style\n";
+        let test_filename = "test_code_lines.rs";
+        File::create(test_filename).unwrap().write_all(test_content.as_bytes()).unwrap();
+
+        let slashes = count_code_lines(Input::File(test_filename.to_string()), "//").unwrap();
+        let hashes = count_code_lines(Input::File(test_filename.to_string()), "#").unwrap();
+        let columns = columns_for(&["--code-lines", "--comment-prefix=#"], test_filename);
+        fs::remove_file(test_filename).unwrap();
+
+        // fn main() {, let x = 1; and } plus "# shell style", which isn't a // comment
+        assert_eq!(slashes, 4);
+        assert_eq!(hashes, 6);
+        assert_eq!(columns, vec![("code_lines", Stat::Count(6))]);
+
+        let args: Vec<String> = ["rowc", "--comment-prefix=#", "f.rs"].iter().map(|s| s.to_string()).collect();
+        assert!(parse_args(&args).is_err());
+        let args: Vec<String> = ["rowc", "--code-lines", "--comment-prefix=", "f.rs"].iter().map(|s| s.to_string()).collect();
+        assert!(parse_args(&args).is_err());
+    }
+
+    #[test]
+    fn test_stdin_name() {
+        let columns = vec![("lines", Stat::Count(2)), ("words", Stat::Count(5)), ("bytes", Stat::Count(24))];
+        let args: Vec<String> = ["rowc", "--stdin-name=clipboard"].iter().map(|s| s.to_string()).collect();
+        let config = parse_args(&args).unwrap();
+        let label = input_label(&config, &config.inputs[0]);
+        assert_eq!(label, Some("clipboard"));
+        assert_eq!(format_plain(&columns, label, false), "       2        5       24 clipboard");
+        assert_eq!(format_jsonl(&columns, label), r#"{"file":"clipboard","lines":2,"words":5,"bytes":24}"#);
+
+        // Files keep their own names, and stdin stays unlabelled by default
+        let file = Input::File("notes.txt".to_string());
+        assert_eq!(input_label(&config, &file), Some("notes.txt"));
+        let config = parse_args(&["rowc".to_string()]).unwrap();
+        assert_eq!(input_label(&config, &config.inputs[0]), None);
+    }
+
+    #[test]
+    fn test_output_file() {
+        let test_filename = "test_output_input.txt";
+        let report = "test_output_report.txt";
+        File::create(test_filename).unwrap().write_all(b"one two\nthree\n").unwrap();
+        File::create(report).unwrap().write_all(b"stale contents that should be truncated away\n").unwrap();
+
+        let args: Vec<String> = ["rowc", "--output=test_output_report.txt", test_filename, test_filename].iter().map(|s| s.to_string()).collect();
+        let config = parse_args(&args).unwrap();
+        assert_eq!(config.output.as_deref(), Some(report));
+        let mut stdout = Vec::new();
+        assert!(run(&config, &mut stdout));
+        assert!(run_to_file(&config, report).unwrap());
+        let written = fs::read(report).unwrap();
+        fs::remove_file(test_filename).unwrap();
+        fs::remove_file(report).unwrap();
+
+        assert_eq!(written, stdout);
+        assert!(String::from_utf8(written).unwrap().ends_with(" total\n"));
+        assert!(run_to_file(&config, "test_output_missing_dir/report.txt").is_err());
+    }
+
+    #[test]
+    fn test_words_per_line() {
+        let test_filename = "test_words_per_line.txt";
+        File::create(test_filename).unwrap().write_all(b"one two three\n\nfour\n  five six  \n").unwrap();
+
+        let args: Vec<String> = ["rowc", "--words-per-line", test_filename].iter().map(|s| s.to_string()).collect();
+        let mut out = Vec::new();
+        let ok = run(&parse_args(&args).unwrap(), &mut out);
+        let args: Vec<String> = ["rowc", "--words-per-line", "--jsonl", test_filename].iter().map(|s| s.to_string()).collect();
+        let mut jsonl = Vec::new();
+        run(&parse_args(&args).unwrap(), &mut jsonl);
+        fs::remove_file(test_filename).unwrap();
+
+        assert!(ok);
+        assert_eq!(String::from_utf8(out).unwrap(), concat!(
+            "       1        3\n",
+            "       2        0\n",
+            "       3        1\n",
+            "       4        2\n",
+            "       0        3      1.5 test_words_per_line.txt\n",
+        ));
+        // Only the summary in JSON lines
+        assert_eq!(String::from_utf8(jsonl).unwrap(), "{\"file\":\"test_words_per_line.txt\",\"min_words\":0,\"max_words\":3,\"avg_words\":1.5}\n");
+        assert_eq!(words_per_line("a b\n\nc", 0), vec![2, 0, 1]);
+        assert!(words_per_line("", 0).is_empty());
+
+        let summary = |min, max, sum, items| vec![("min_words", Stat::Count(min)), ("max_words", Stat::Count(max)), ("avg_words", Stat::Mean { sum, items })];
+        assert_eq!(add_columns(summary(2, 5, 7, 2), &summary(0, 3, 3, 3)), summary(0, 5, 10, 5));
+    }
+
+    #[test]
+    fn test_count_line_endings() {
+        let test_filename = "test_line_endings.txt";
+        File::create(test_filename).unwrap().write_all(b"unix\nwindows\r\nunix\n\r\nold mac\rno terminator").unwrap();
+
+        let columns = columns_for(&["--line-endings"], test_filename);
+        fs::remove_file(test_filename).unwrap();
+
+        assert_eq!(columns, vec![("lf", Stat::Count(2)), ("crlf", Stat::Count(2)), ("cr", Stat::Count(1))]);
+        let endings = count_line_endings(b"\r\r\n\n\r");
+        assert_eq!((endings.lf, endings.crlf, endings.cr), (1, 1, 2));
+    }
+}